@@ -14,6 +14,7 @@ pub const CLAP_FEATURES: &[ClapFeature] = &[
     ClapFeature::AudioEffect,
     ClapFeature::Compressor,
     ClapFeature::Stereo,
+    ClapFeature::Mono,
 ];
 
 pub const VST3_CLASS_ID: [u8; 16] = *b"hCmpVdKz609ecZKi";
@@ -1,2 +1 @@
 pub mod compressor;
-pub mod envelope;
@@ -7,11 +7,16 @@ mod parameters;
 
 use constants::*;
 use dsp::compressor::Compressor;
+use grimoire_dsp::oversampling::{Oversampler, OversamplingFactor, OversamplingPhase};
 use parameters::CantripCompressorParams;
 
 struct CantripCompressor {
     params: Arc<CantripCompressorParams>,
     compressor: Compressor,
+    /// One oversampler per side of the linked detector
+    oversamplers: [Oversampler; 2],
+    /// Whether the mono input needs to be copied to the second output channel
+    mono_to_stereo: bool,
     sample_rate: f32,
 }
 
@@ -20,6 +25,8 @@ impl Default for CantripCompressor {
         Self {
             params: Arc::new(CantripCompressorParams::default()),
             compressor: Compressor::new(),
+            oversamplers: [Oversampler::new(OversamplingFactor::Off, OversamplingPhase::Linear); 2],
+            mono_to_stereo: false,
             sample_rate: 44100.0,
         }
     }
@@ -32,13 +39,29 @@ impl Plugin for CantripCompressor {
     const EMAIL: &'static str = EMAIL;
     const VERSION: &'static str = VERSION;
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
-        aux_input_ports: &[],
-        aux_output_ports: &[],
-        names: PortNames::const_default(),
-    }];
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+    ];
 
     const MIDI_INPUT: MidiConfig = MidiConfig::None;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
@@ -54,25 +77,36 @@ impl Plugin for CantripCompressor {
 
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
+        audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
+        self.mono_to_stereo = audio_io_layout.main_input_channels == NonZeroU32::new(1)
+            && audio_io_layout.main_output_channels == NonZeroU32::new(2);
         self.sample_rate = buffer_config.sample_rate;
         self.compressor.reset();
+        self.update_oversampling();
+        context.set_latency_samples(self.oversamplers[0].latency());
         true
     }
 
     fn reset(&mut self) {
         self.compressor.reset();
+        for oversampler in &mut self.oversamplers {
+            oversampler.reset();
+        }
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
+        if let Some(latency) = self.update_oversampling() {
+            context.set_latency_samples(latency);
+        }
+
         // Get parameter values
         let threshold = self.params.threshold.value();
         let ratio = self.params.ratio.value();
@@ -82,38 +116,53 @@ impl Plugin for CantripCompressor {
         let makeup_db = self.params.makeup.value();
         let mix = self.params.mix.value() / 100.0;
 
-        // Update compressor timing
-        self.compressor.set_times(attack, release, self.sample_rate);
+        // Update compressor timing, the detector runs at the oversampled rate
+        let oversampled_rate = self.sample_rate * self.oversamplers[0].ratio() as f32;
+        self.compressor.set_times(attack, release, oversampled_rate);
 
         // Convert makeup gain to linear
         let makeup_gain = 10.0f32.powf(makeup_db / 20.0);
 
         // Process sample by sample
         for mut channel_samples in buffer.iter_samples() {
-            // Get stereo samples
-            let mut samples: [f32; 2] = [0.0; 2];
-            for (i, sample) in channel_samples.iter_mut().enumerate() {
-                if i < 2 {
-                    samples[i] = *sample;
+            if self.mono_to_stereo {
+                // Only the first channel holds input, the second one is ours to fill
+                let mono = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+                if let Some(right) = channel_samples.get_mut(1) {
+                    *right = mono;
                 }
             }
 
-            // Compute gain reduction (linked stereo)
-            let gain = self.compressor.process_stereo(
-                samples[0],
-                samples[1],
-                threshold,
-                ratio,
-                knee,
-            );
-
-            // Apply gain with makeup and mix
-            for (i, sample) in channel_samples.iter_mut().enumerate() {
-                if i < 2 {
-                    let dry = samples[i];
-                    let wet = samples[i] * gain * makeup_gain;
-                    *sample = dry * (1.0 - mix) + wet * mix;
-                }
+            // With a single channel both sides of the detector see the same signal
+            let last_channel = channel_samples.len() - 1;
+            let left = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+            let right = channel_samples
+                .get_mut(last_channel)
+                .map_or(0.0, |sample| *sample);
+
+            // The dry signal goes through the oversampler as well, so it stays aligned with the
+            // wet signal in the mix
+            let [left_oversampler, right_oversampler] = &mut self.oversamplers;
+            let left_samples = left_oversampler.upsample(left);
+            let right_samples = right_oversampler.upsample(right);
+            for (left, right) in left_samples.iter_mut().zip(right_samples.iter_mut()) {
+                // Compute gain reduction (linked stereo)
+                let gain = self
+                    .compressor
+                    .process_stereo(*left, *right, threshold, ratio, knee);
+
+                // Apply gain with makeup and mix
+                let mixed_gain = (1.0 - mix) + gain * makeup_gain * mix;
+                *left *= mixed_gain;
+                *right *= mixed_gain;
+            }
+            let outputs = [
+                left_oversampler.downsample(),
+                right_oversampler.downsample(),
+            ];
+
+            for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+                *sample = outputs[channel_idx.min(1)];
             }
         }
 
@@ -121,6 +170,23 @@ impl Plugin for CantripCompressor {
     }
 }
 
+impl CantripCompressor {
+    /// Follow the oversampling parameters. Returns the new latency if the mode has changed.
+    fn update_oversampling(&mut self) -> Option<u32> {
+        let factor = self.params.oversampling.value();
+        let phase = self.params.oversampling_phase.value();
+        if factor == self.oversamplers[0].factor() && phase == self.oversamplers[0].phase() {
+            return None;
+        }
+
+        for oversampler in &mut self.oversamplers {
+            oversampler.set_mode(factor, phase);
+        }
+
+        Some(self.oversamplers[0].latency())
+    }
+}
+
 impl ClapPlugin for CantripCompressor {
     const CLAP_ID: &'static str = CLAP_ID;
     const CLAP_DESCRIPTION: Option<&'static str> = CLAP_DESCRIPTION;
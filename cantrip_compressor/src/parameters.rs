@@ -1,5 +1,7 @@
 use nih_plug::prelude::*;
 
+use grimoire_dsp::oversampling::{OversamplingFactor, OversamplingPhase};
+
 #[derive(Params)]
 pub struct CantripCompressorParams {
     /// Threshold in dB - level above which compression begins
@@ -29,6 +31,14 @@ pub struct CantripCompressorParams {
     /// Mix (dry/wet) - 0% = dry, 100% = wet
     #[id = "mix"]
     pub mix: FloatParam,
+
+    /// Runs the detector and gain stage at a multiple of the host sample rate, which catches
+    /// peaks between samples and keeps fast gain changes from aliasing
+    #[id = "oversampling"]
+    pub oversampling: EnumParam<OversamplingFactor>,
+
+    #[id = "oversampling_phase"]
+    pub oversampling_phase: EnumParam<OversamplingPhase>,
 }
 
 impl Default for CantripCompressorParams {
@@ -113,6 +123,10 @@ impl Default for CantripCompressorParams {
             )
             .with_unit("%")
             .with_step_size(1.0),
+
+            oversampling: EnumParam::new("Oversampling", OversamplingFactor::Off),
+
+            oversampling_phase: EnumParam::new("Oversampling Phase", OversamplingPhase::Linear),
         }
     }
 }
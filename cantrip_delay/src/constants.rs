@@ -13,8 +13,15 @@ pub const CLAP_SUPPORT_URL: Option<&str> = None;
 pub const CLAP_FEATURES: &[ClapFeature] = &[
     ClapFeature::AudioEffect,
     ClapFeature::Delay,
+    ClapFeature::Chorus,
+    ClapFeature::Flanger,
     ClapFeature::Stereo,
+    ClapFeature::Mono,
 ];
 
 pub const VST3_CLASS_ID: [u8; 16] = *b"CantripDelay0001";
-pub const VST3_SUBCATEGORIES: &[Vst3SubCategory] = &[Vst3SubCategory::Fx, Vst3SubCategory::Delay];
+pub const VST3_SUBCATEGORIES: &[Vst3SubCategory] = &[
+    Vst3SubCategory::Fx,
+    Vst3SubCategory::Delay,
+    Vst3SubCategory::Modulation,
+];
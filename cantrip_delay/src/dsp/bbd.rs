@@ -0,0 +1,215 @@
+use std::f32::consts::TAU;
+
+use grimoire_dsp::envelope::EnvelopeFollower;
+use grimoire_dsp::svf::Svf;
+
+use super::Noise;
+use crate::parameters::NoiseType;
+
+/// Number of buckets in the emulated chip, the same as an MN3005.
+const STAGES: f32 = 4096.0;
+
+/// Cutoff of the anti-aliasing and reconstruction filters as a fraction of the clock rate.
+const FILTER_CUTOFF: f32 = 0.4;
+
+/// Highest filter cutoff, real units use fixed filters tuned for their shortest delay.
+const MAX_FILTER_HZ: f32 = 8000.0;
+
+/// Q of the two stages of the 24 dB/oct Butterworth filters.
+const FILTER_Q: [f32; 2] = [0.541_196_1, 1.306_563];
+
+/// Attack and release of the compander's envelope detectors in milliseconds.
+const COMPANDER_ATTACK_MS: f32 = 1.0;
+const COMPANDER_RELEASE_MS: f32 = 30.0;
+
+/// Lowest envelope the compressor reacts to, which limits its gain to 30 dB.
+const COMPANDER_FLOOR: f32 = 1e-3;
+
+/// Level of the chip's own hiss, added between the compressor and the expander.
+const NOISE_LEVEL: f32 = 0.002;
+
+/// Level of the clock signal leaking into the output before the reconstruction filter.
+const CLOCK_LEAK: f32 = 0.003;
+
+/// Bucket brigade delay chip with its companion circuitry.
+///
+/// A BBD delays the signal by passing it through a chain of capacitors at the clock rate, so
+/// a longer delay means a lower clock rate and a lower bandwidth. The actual delay still comes
+/// from a [`super::DelayLine`]: [`Bbd::encode`] goes in front of it and [`Bbd::decode`] after it.
+///
+/// The input is compressed 2:1 and band limited, then sampled at the clock rate. On the way out
+/// the signal goes through the reconstruction filter and is expanded again. The chip's hiss and
+/// the leaking clock sit between the compressor and the expander, so they pump with the signal.
+pub struct Bbd {
+    anti_alias: [Svf; 2],
+    reconstruction: [Svf; 2],
+    compressor: EnvelopeFollower,
+    expander: EnvelopeFollower,
+    noise: Noise,
+    delay_ms: f32,
+    /// Clock rate relative to the host rate, `1.0` or above disables the sample and hold
+    clock_ratio: f32,
+    phase: f32,
+    held: f32,
+    sample_rate: f32,
+}
+
+impl Bbd {
+    pub fn new(seed: u32, sample_rate: f32) -> Self {
+        let mut bbd = Self {
+            anti_alias: [Svf::new(); 2],
+            reconstruction: [Svf::new(); 2],
+            compressor: EnvelopeFollower::default(),
+            expander: EnvelopeFollower::default(),
+            noise: Noise::new(seed, sample_rate),
+            delay_ms: 0.0,
+            clock_ratio: 1.0,
+            phase: 0.0,
+            held: 0.0,
+            sample_rate,
+        };
+        bbd.set_sample_rate(sample_rate);
+        bbd
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.compressor
+            .set_times(COMPANDER_ATTACK_MS, COMPANDER_RELEASE_MS, sample_rate);
+        self.expander
+            .set_times(COMPANDER_ATTACK_MS, COMPANDER_RELEASE_MS, sample_rate);
+        self.noise.set_sample_rate(sample_rate);
+
+        // Force the filters to be recalculated for the new rate
+        self.delay_ms = 0.0;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        for filter in self.anti_alias.iter_mut().chain(&mut self.reconstruction) {
+            filter.reset();
+        }
+        self.compressor.reset();
+        self.expander.reset();
+        self.noise.reset();
+        self.phase = 0.0;
+        self.held = 0.0;
+    }
+
+    /// Set the delay time, which determines the clock rate and with it the bandwidth.
+    pub fn set_delay(&mut self, delay_ms: f32) {
+        if delay_ms == self.delay_ms {
+            return;
+        }
+        self.delay_ms = delay_ms;
+
+        // Every bucket is passed on once per two clock cycles
+        let clock_hz = STAGES / (2.0 * delay_ms * 0.001);
+        self.clock_ratio = clock_hz / self.sample_rate;
+
+        let cutoff = (clock_hz * FILTER_CUTOFF).min(MAX_FILTER_HZ);
+        for (filter, q) in self.anti_alias.iter_mut().zip(FILTER_Q) {
+            filter.update(cutoff, q, self.sample_rate);
+        }
+        for (filter, q) in self.reconstruction.iter_mut().zip(FILTER_Q) {
+            filter.update(cutoff, q, self.sample_rate);
+        }
+    }
+
+    /// Compress, filter and sample the signal going into the delay line.
+    pub fn encode(&mut self, input: f32) -> f32 {
+        let envelope = self.compressor.process(input).max(COMPANDER_FLOOR);
+        let compressed = input / envelope.sqrt();
+
+        let filtered = self
+            .anti_alias
+            .iter_mut()
+            .fold(compressed, |sample, filter| filter.process(sample).low);
+
+        let sampled = if self.clock_ratio < 1.0 {
+            self.phase += self.clock_ratio;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+                self.held = filtered;
+            }
+            self.held
+        } else {
+            filtered
+        };
+
+        sampled + self.noise.next(NoiseType::Tape) * NOISE_LEVEL
+    }
+
+    /// Reconstruct and expand the signal coming out of the delay line.
+    pub fn decode(&mut self, output: f32) -> f32 {
+        // Above Nyquist the clock would only alias, the real filters remove it long before that
+        let clock = if self.clock_ratio < 0.5 {
+            (TAU * self.phase).sin() * CLOCK_LEAK
+        } else {
+            0.0
+        };
+
+        let reconstructed = self
+            .reconstruction
+            .iter_mut()
+            .fold(output + clock, |sample, filter| filter.process(sample).low);
+
+        // Undo the 2:1 compression by scaling with the compressed signal's own envelope
+        reconstructed * self.expander.process(reconstructed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_peak(bbd: &mut Bbd, freq: f32, amplitude: f32) -> f32 {
+        let sample_rate = 44100.0;
+        let mut peak = 0.0f32;
+        for i in 0..(sample_rate as usize) {
+            let input = amplitude * (TAU * freq * i as f32 / sample_rate).sin();
+            let encoded = bbd.encode(input);
+            let output = bbd.decode(encoded);
+            // Skip the settling time
+            if i > sample_rate as usize / 2 {
+                peak = peak.max(output.abs());
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn test_bbd_compander_keeps_level() {
+        let mut bbd = Bbd::new(1, 44100.0);
+        bbd.set_delay(100.0);
+
+        let peak = sine_peak(&mut bbd, 100.0, 0.5);
+        assert!(
+            (peak - 0.5).abs() < 0.1,
+            "Expected the level to be restored, got {}",
+            peak
+        );
+    }
+
+    #[test]
+    fn test_bbd_bandwidth_follows_delay_time() {
+        let mut short = Bbd::new(1, 44100.0);
+        short.set_delay(50.0);
+        let mut long = Bbd::new(1, 44100.0);
+        long.set_delay(1000.0);
+
+        let short_peak = sine_peak(&mut short, 3000.0, 0.5);
+        let long_peak = sine_peak(&mut long, 3000.0, 0.5);
+
+        assert!(
+            short_peak > 0.3,
+            "Expected 3kHz to pass, got {}",
+            short_peak
+        );
+        assert!(
+            long_peak < 0.05,
+            "Expected 3kHz to be filtered out, got {}",
+            long_peak
+        );
+    }
+}
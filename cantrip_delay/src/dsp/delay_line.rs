@@ -6,9 +6,8 @@ pub struct DelayLine {
 
 impl DelayLine {
     pub fn new(max_delay_ms: f32, sample_rate: f32) -> Self {
-        let max_samples = (max_delay_ms * sample_rate / 1000.0).ceil() as usize + 1;
         Self {
-            buffer: vec![0.0; max_samples],
+            buffer: vec![0.0; Self::buffer_len(max_delay_ms, sample_rate)],
             write_pos: 0,
             sample_rate,
         }
@@ -16,8 +15,8 @@ impl DelayLine {
 
     pub fn set_sample_rate(&mut self, sample_rate: f32, max_delay_ms: f32) {
         self.sample_rate = sample_rate;
-        let max_samples = (max_delay_ms * sample_rate / 1000.0).ceil() as usize + 1;
-        self.buffer.resize(max_samples, 0.0);
+        self.buffer
+            .resize(Self::buffer_len(max_delay_ms, sample_rate), 0.0);
         self.reset();
     }
 
@@ -26,26 +25,56 @@ impl DelayLine {
         self.write_pos = 0;
     }
 
-    pub fn process(&mut self, input: f32, delay_ms: f32, feedback: f32) -> f32 {
-        let delay_samples = (delay_ms * self.sample_rate / 1000.0) as usize;
-        let delay_samples = delay_samples.min(self.buffer.len() - 1);
+    /// Convert a time in milliseconds to a (fractional) number of samples.
+    pub fn ms_to_samples(&self, ms: f32) -> f32 {
+        ms * self.sample_rate / 1000.0
+    }
 
-        let read_pos = if self.write_pos >= delay_samples {
-            self.write_pos - delay_samples
-        } else {
-            self.buffer.len() - (delay_samples - self.write_pos)
-        };
+    /// Read the signal `delay_samples` behind the write head.
+    ///
+    /// Fractional delays are linearly interpolated, so the read position can be modulated
+    /// smoothly. The delay is clamped to what the buffer can hold.
+    pub fn read(&self, delay_samples: f32) -> f32 {
+        let max_delay = (self.buffer.len() - 2) as f32;
+        let delay_samples = delay_samples.clamp(1.0, max_delay);
+
+        let whole = delay_samples as usize;
+        let frac = delay_samples - whole as f32;
 
-        let delayed = self.buffer[read_pos];
+        let newer = self.buffer[self.wrap_back(whole)];
+        let older = self.buffer[self.wrap_back(whole + 1)];
 
-        self.buffer[self.write_pos] = input + delayed * feedback;
+        newer + (older - newer) * frac
+    }
+
+    /// Write a sample at the write head and advance it.
+    pub fn write(&mut self, sample: f32) {
+        self.buffer[self.write_pos] = sample;
 
         self.write_pos += 1;
         if self.write_pos >= self.buffer.len() {
             self.write_pos = 0;
         }
+    }
 
-        delayed
+    /// Write a sample, crossfading it with a copy of the signal `loop_samples` behind the write
+    /// head. At a `hold` of 1.0 the line loops its contents unchanged.
+    pub fn write_held(&mut self, sample: f32, loop_samples: usize, hold: f32) {
+        let held = self.buffer[self.wrap_back(loop_samples.clamp(1, self.buffer.len() - 2))];
+        self.write(sample + (held - sample) * hold);
+    }
+
+    fn wrap_back(&self, offset: usize) -> usize {
+        if self.write_pos >= offset {
+            self.write_pos - offset
+        } else {
+            self.buffer.len() - (offset - self.write_pos)
+        }
+    }
+
+    fn buffer_len(max_delay_ms: f32, sample_rate: f32) -> usize {
+        // One extra sample for the write head and one for the interpolation neighbour
+        (max_delay_ms * sample_rate / 1000.0).ceil() as usize + 2
     }
 }
 
@@ -56,36 +85,42 @@ mod tests {
     #[test]
     fn test_delay_line_basic() {
         let mut delay = DelayLine::new(100.0, 1000.0);
+        let delay_samples = delay.ms_to_samples(10.0);
 
-        delay.process(1.0, 10.0, 0.0);
+        delay.write(1.0);
 
         for _ in 0..9 {
-            let output = delay.process(0.0, 10.0, 0.0);
-            assert_eq!(output, 0.0);
+            assert_eq!(delay.read(delay_samples), 0.0);
+            delay.write(0.0);
         }
 
-        let output = delay.process(0.0, 10.0, 0.0);
-        assert_eq!(output, 1.0);
+        assert_eq!(delay.read(delay_samples), 1.0);
     }
 
     #[test]
     fn test_delay_line_feedback() {
         let mut delay = DelayLine::new(100.0, 1000.0);
+        let delay_samples = delay.ms_to_samples(10.0);
+        let step = |delay: &mut DelayLine, input: f32| {
+            let delayed = delay.read(delay_samples);
+            delay.write(input + delayed * 0.5);
+            delayed
+        };
 
-        delay.process(1.0, 10.0, 0.5);
+        step(&mut delay, 1.0);
 
         for _ in 0..9 {
-            delay.process(0.0, 10.0, 0.5);
+            step(&mut delay, 0.0);
         }
 
-        let output = delay.process(0.0, 10.0, 0.5);
+        let output = step(&mut delay, 0.0);
         assert_eq!(output, 1.0);
 
         for _ in 0..9 {
-            delay.process(0.0, 10.0, 0.5);
+            step(&mut delay, 0.0);
         }
 
-        let output = delay.process(0.0, 10.0, 0.5);
+        let output = step(&mut delay, 0.0);
         assert!((output - 0.5).abs() < 0.001);
     }
 
@@ -93,12 +128,44 @@ mod tests {
     fn test_delay_line_reset() {
         let mut delay = DelayLine::new(100.0, 1000.0);
 
-        delay.process(1.0, 10.0, 0.5);
+        delay.write(1.0);
         delay.reset();
 
         for _ in 0..20 {
-            let output = delay.process(0.0, 10.0, 0.5);
-            assert_eq!(output, 0.0);
+            assert_eq!(delay.read(delay.ms_to_samples(10.0)), 0.0);
+            delay.write(0.0);
         }
     }
+
+    #[test]
+    fn test_delay_line_fractional_read() {
+        let mut delay = DelayLine::new(100.0, 1000.0);
+
+        delay.write(1.0);
+        delay.write(0.0);
+
+        // Halfway between one and two samples ago
+        assert!((delay.read(1.5) - 0.5).abs() < 1e-6);
+        assert!((delay.read(1.25) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_delay_line_write_held_loops_unchanged() {
+        let mut delay = DelayLine::new(100.0, 1000.0);
+
+        for n in 0..50 {
+            delay.write((n as f32 * 0.3).sin());
+        }
+        let energy =
+            |delay: &DelayLine| (1..=10).map(|n| delay.read(n as f32).powi(2)).sum::<f32>();
+        let before = energy(&delay);
+
+        // Ten seconds of lossy feedback don't touch the held loop
+        for _ in 0..10000 {
+            let feedback = delay.read(10.0) * 0.5;
+            delay.write_held(feedback, 10, 1.0);
+        }
+
+        assert!((energy(&delay) - before).abs() < 1e-6);
+    }
 }
@@ -0,0 +1,135 @@
+use grimoire_dsp::lfo::Lfo;
+
+use super::DelayLine;
+
+/// Delay times of the all-pass stages in milliseconds, mutually prime-ish to avoid ringing.
+const STAGE_MS: [f32; 4] = [1.71, 2.89, 4.13, 5.37];
+/// Modulation depth of every stage at full diffusion in milliseconds.
+const MOD_DEPTH_MS: f32 = 0.2;
+const MOD_RATE_HZ: f32 = 0.4;
+/// All-pass gain at full diffusion.
+const MAX_GAIN: f32 = 0.7;
+
+/// Upper bound of the chain's latency in milliseconds, with room for the stages rounding up.
+pub const DIFFUSER_MAX_LATENCY_MS: f32 =
+    STAGE_MS[0] + STAGE_MS[1] + STAGE_MS[2] + STAGE_MS[3] + 1.0;
+
+/// A series of short, slowly modulated Schroeder all-pass filters that smear transients.
+///
+/// The all-pass gain scales with the diffusion amount, so the signal always takes the same path
+/// and the amount can move without clicks. At zero the chain is a pure delay of
+/// [`latency_samples()`](Self::latency_samples), which callers can subtract from their own delay
+/// time so the echoes stay on time at every amount.
+pub struct Diffuser {
+    stages: [DelayLine; 4],
+    /// Stage delays rounded to whole samples, so the unmodulated chain doesn't interpolate
+    stage_samples: [f32; 4],
+    /// One LFO per stage, spread evenly over the cycle
+    lfos: [Lfo; 4],
+}
+
+impl Diffuser {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            stages: STAGE_MS.map(|ms| DelayLine::new(ms + MOD_DEPTH_MS, sample_rate)),
+            stage_samples: Self::stage_samples(sample_rate),
+            lfos: std::array::from_fn(|stage_idx| {
+                let mut lfo = Lfo::new();
+                lfo.set_rate(MOD_RATE_HZ, sample_rate);
+                lfo.set_phase_offset(stage_idx as f32 / STAGE_MS.len() as f32);
+                lfo
+            }),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for (stage, ms) in self.stages.iter_mut().zip(STAGE_MS) {
+            stage.set_sample_rate(sample_rate, ms + MOD_DEPTH_MS);
+        }
+        self.stage_samples = Self::stage_samples(sample_rate);
+        for lfo in &mut self.lfos {
+            lfo.set_rate(MOD_RATE_HZ, sample_rate);
+            lfo.reset();
+        }
+    }
+
+    /// Total delay of the all-pass stages in samples.
+    pub fn latency_samples(&self) -> f32 {
+        self.stage_samples.iter().sum()
+    }
+
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+        for lfo in &mut self.lfos {
+            lfo.reset();
+        }
+    }
+
+    /// Process a single sample. `amount` ranges from 0.0 (bypassed) to 1.0 (fully diffused).
+    pub fn process(&mut self, input: f32, amount: f32) -> f32 {
+        let gain = MAX_GAIN * amount;
+        let mod_depth = self.stages[0].ms_to_samples(MOD_DEPTH_MS) * amount;
+
+        let mut signal = input;
+        for ((stage, samples), lfo) in self
+            .stages
+            .iter_mut()
+            .zip(self.stage_samples)
+            .zip(&mut self.lfos)
+        {
+            let delayed = stage.read(samples + lfo.process() * mod_depth);
+
+            let v = signal + gain * delayed;
+            stage.write(v);
+            signal = delayed - gain * v;
+        }
+
+        signal
+    }
+
+    fn stage_samples(sample_rate: f32) -> [f32; 4] {
+        STAGE_MS.map(|ms| (ms * sample_rate / 1000.0).round())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diffuser_delays_at_zero_amount() {
+        let mut diffuser = Diffuser::new(44100.0);
+        let latency = diffuser.latency_samples() as usize;
+
+        // A pure delay, without any smearing from interpolation
+        let input = |n: usize| (n as f32 * 0.1).sin();
+        for n in 0..latency + 100 {
+            let expected = if n >= latency {
+                input(n - latency)
+            } else {
+                0.0
+            };
+            assert_eq!(diffuser.process(input(n), 0.0), expected);
+        }
+    }
+
+    #[test]
+    fn test_diffuser_smears_impulse() {
+        let mut diffuser = Diffuser::new(44100.0);
+
+        let mut energy = 0.0;
+        let mut peak = 0.0f32;
+        for n in 0..44100 {
+            let input = if n == 0 { 1.0 } else { 0.0 };
+            let output = diffuser.process(input, 1.0);
+            energy += output * output;
+            peak = peak.max(output.abs());
+        }
+
+        // The impulse is spread out over time without gaining energy
+        assert!(peak < 0.5, "Expected a smeared impulse, got peak {}", peak);
+        assert!(energy <= 1.0, "Expected no gain, got energy {}", energy);
+    }
+}
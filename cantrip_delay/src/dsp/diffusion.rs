@@ -0,0 +1,246 @@
+use super::{DelayLine, Diffuser, DIFFUSER_MAX_LATENCY_MS};
+use crate::parameters::DiffusionPosition;
+
+/// Crossfade time between the bypassed and the diffused signal in milliseconds.
+const FADE_MS: f32 = 20.0;
+
+/// Places a [`Diffuser`] in the delay and keeps the echoes on time around it.
+///
+/// The diffuser delays everything passing through it by its latency, so the delay line is read
+/// that much earlier to make up for it. Before the delay, the diffuser only sits on the path from
+/// the input to the first echo, so the feedback is delayed by the same amount to keep the loop at
+/// the full delay time. In the feedback loop, the diffuser itself delays every repeat.
+///
+/// The latency can't be taken off delays shorter than itself, so at zero diffusion the diffuser
+/// is bypassed altogether and crossfaded back in once diffusion goes above zero.
+pub struct Diffusion {
+    diffuser: Diffuser,
+    /// Delays the feedback by the compensation in the pre position
+    feedback_delay: DelayLine,
+    position: DiffusionPosition,
+    amount: f32,
+    /// Crossfade between the bypassed (0.0) and the diffused (1.0) signal
+    fade: f32,
+    fade_step: f32,
+    /// Latency taken off the last read, in samples
+    compensation: f32,
+}
+
+impl Diffusion {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            diffuser: Diffuser::new(sample_rate),
+            feedback_delay: DelayLine::new(DIFFUSER_MAX_LATENCY_MS, sample_rate),
+            position: DiffusionPosition::Feedback,
+            amount: 0.0,
+            fade: 0.0,
+            fade_step: Self::fade_step(sample_rate),
+            compensation: 0.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.diffuser.set_sample_rate(sample_rate);
+        self.feedback_delay
+            .set_sample_rate(sample_rate, DIFFUSER_MAX_LATENCY_MS);
+        self.fade_step = Self::fade_step(sample_rate);
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.diffuser.reset();
+        self.feedback_delay.reset();
+        self.fade = 0.0;
+        self.compensation = 0.0;
+    }
+
+    pub fn set_position(&mut self, position: DiffusionPosition) {
+        self.position = position;
+    }
+
+    /// Set the diffusion amount for the next sample, from 0.0 (bypassed) to 1.0 (fully diffused).
+    pub fn set_amount(&mut self, amount: f32) {
+        self.amount = amount;
+        self.fade = if amount > 0.0 {
+            (self.fade + self.fade_step).min(1.0)
+        } else {
+            (self.fade - self.fade_step).max(0.0)
+        };
+    }
+
+    /// Diffuse the input on its way into the delay line, if the diffuser sits before the delay.
+    pub fn process_input(&mut self, input: f32) -> f32 {
+        match self.position {
+            DiffusionPosition::Pre => {
+                let diffused = self.diffuser.process(input, self.amount);
+                input + (diffused - input) * self.fade
+            }
+            DiffusionPosition::Feedback => input,
+        }
+    }
+
+    /// Read the echo `delay_samples` behind the write head, taking the diffuser's latency off the
+    /// read as far as the delay time allows.
+    pub fn read(&mut self, delay_line: &DelayLine, delay_samples: f32) -> f32 {
+        self.compensation = self
+            .diffuser
+            .latency_samples()
+            .min(delay_samples - 1.0)
+            .max(0.0);
+
+        let plain = delay_line.read(delay_samples);
+        let compensated = delay_line.read(delay_samples - self.compensation);
+        let diffused = match self.position {
+            DiffusionPosition::Pre => compensated,
+            DiffusionPosition::Feedback => self.diffuser.process(compensated, self.amount),
+        };
+
+        plain + (diffused - plain) * self.fade
+    }
+
+    /// Diffuse an echo that was read without compensation, like the reverse mode's chunks.
+    pub fn process_uncompensated(&mut self, wet: f32) -> f32 {
+        self.compensation = 0.0;
+
+        match self.position {
+            DiffusionPosition::Pre => wet,
+            DiffusionPosition::Feedback => {
+                let diffused = self.diffuser.process(wet, self.amount);
+                wet + (diffused - wet) * self.fade
+            }
+        }
+    }
+
+    /// Delay the feedback by the compensation of the last read if the diffuser sits before the
+    /// delay, so the loop stays as long as the delay time.
+    pub fn process_feedback(&mut self, feedback: f32) -> f32 {
+        self.feedback_delay.write(feedback);
+
+        match self.position {
+            DiffusionPosition::Pre => {
+                // The sample just written is one sample behind the write head
+                let delayed = self.feedback_delay.read(self.compensation + 1.0);
+                feedback + (delayed - feedback) * self.fade
+            }
+            DiffusionPosition::Feedback => feedback,
+        }
+    }
+
+    fn fade_step(sample_rate: f32) -> f32 {
+        1000.0 / (FADE_MS * sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Send an impulse through a delay with diffusion and feedback, returning the wet signal.
+    fn impulse_response(
+        position: DiffusionPosition,
+        amount: f32,
+        delay_ms: f32,
+        samples: usize,
+    ) -> Vec<f32> {
+        let mut delay_line = DelayLine::new(100.0, SAMPLE_RATE);
+        let mut diffusion = Diffusion::new(SAMPLE_RATE);
+        diffusion.set_position(position);
+
+        // Fade the diffuser in before the impulse arrives
+        let fade_in = (FADE_MS * SAMPLE_RATE / 1000.0) as usize + 1;
+        let delay_samples = delay_line.ms_to_samples(delay_ms);
+
+        let mut output = Vec::with_capacity(samples);
+        for n in 0..fade_in + samples {
+            let input = if n == fade_in { 1.0 } else { 0.0 };
+
+            diffusion.set_amount(amount);
+            let input = diffusion.process_input(input);
+            let wet = diffusion.read(&delay_line, delay_samples);
+            let feedback = diffusion.process_feedback(wet);
+            delay_line.write(input + feedback * 0.5);
+
+            if n >= fade_in {
+                output.push(wet);
+            }
+        }
+
+        output
+    }
+
+    /// Position of the loudest sample in `range`.
+    fn peak_in(output: &[f32], range: std::ops::Range<usize>) -> usize {
+        let start = range.start;
+        output[range]
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .map(|(idx, _)| start + idx)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_diffusion_bypassed_at_zero_amount() {
+        // Far shorter than the diffuser's latency
+        let delay = (5.0 * SAMPLE_RATE / 1000.0) as usize;
+
+        for position in [DiffusionPosition::Pre, DiffusionPosition::Feedback] {
+            let output = impulse_response(position, 0.0, 5.0, 3 * delay);
+
+            for (n, sample) in output.iter().enumerate() {
+                let expected = match n {
+                    n if n == delay => 1.0,
+                    n if n == 2 * delay => 0.5,
+                    _ => 0.0,
+                };
+                assert_eq!(*sample, expected, "{:?} at sample {}", position, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_diffusion_keeps_echoes_on_time() {
+        let delay = (20.0 * SAMPLE_RATE / 1000.0) as usize;
+
+        for position in [DiffusionPosition::Pre, DiffusionPosition::Feedback] {
+            // Light enough for the main tap to stand out after diffusing twice in the feedback loop
+            let output = impulse_response(position, 0.2, 20.0, 3 * delay - delay / 2);
+
+            let first = peak_in(&output, delay / 2..delay + delay / 2);
+            let second = peak_in(&output, delay + delay / 2..output.len());
+            assert!(
+                first.abs_diff(delay) <= 1,
+                "{:?}: expected the first echo at {}, got {}",
+                position,
+                delay,
+                first
+            );
+            assert!(
+                second.abs_diff(2 * delay) <= 1,
+                "{:?}: expected the second echo at {}, got {}",
+                position,
+                2 * delay,
+                second
+            );
+        }
+    }
+
+    #[test]
+    fn test_diffusion_keeps_loop_length_before_short_delay() {
+        // Too short to take the latency off the first echo, but the repeats keep their spacing
+        let delay = (5.0 * SAMPLE_RATE / 1000.0) as usize;
+        let output = impulse_response(DiffusionPosition::Pre, 0.5, 5.0, 8 * delay);
+
+        let latency = Diffuser::new(SAMPLE_RATE).latency_samples() as usize;
+        let first = peak_in(&output, 0..latency + delay / 2);
+        let second = peak_in(&output, first + delay / 2..first + delay + delay / 2);
+        assert!(
+            (second - first).abs_diff(delay) <= 1,
+            "Expected repeats {} samples apart, got {}",
+            delay,
+            second - first
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use grimoire_dsp::envelope::EnvelopeFollower;
+
+/// Ducks the wet signal while the dry input is playing.
+///
+/// The wet signal is turned down by as many dB as the input envelope exceeds the threshold, up
+/// to a maximum amount.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ducker {
+    envelope: EnvelopeFollower,
+}
+
+impl Ducker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the envelope state.
+    pub fn reset(&mut self) {
+        self.envelope.reset();
+    }
+
+    /// Update the envelope follower timing.
+    pub fn set_times(&mut self, attack_ms: f32, release_ms: f32, sample_rate: f32) {
+        self.envelope.set_times(attack_ms, release_ms, sample_rate);
+    }
+
+    /// Follow the dry input and return the gain to apply to the wet signal (linear).
+    ///
+    /// # Arguments
+    /// * `input` - Dry input sample, usually the peak of all channels
+    /// * `threshold_db` - Input level in dB above which the wet signal is ducked
+    /// * `amount_db` - Maximum attenuation in dB
+    pub fn process(&mut self, input: f32, threshold_db: f32, amount_db: f32) -> f32 {
+        let envelope = self.envelope.process(input);
+
+        // Convert to dB (with floor to avoid -inf)
+        let input_db = if envelope > 1e-10 {
+            20.0 * envelope.log10()
+        } else {
+            -100.0
+        };
+
+        let reduction_db = (input_db - threshold_db).clamp(0.0, amount_db.max(0.0));
+
+        10.0f32.powf(-reduction_db / 20.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ducker_passes_wet_below_threshold() {
+        let mut ducker = Ducker::new();
+        ducker.set_times(1.0, 100.0, 44100.0);
+
+        let mut gain = 0.0;
+        for _ in 0..1000 {
+            gain = ducker.process(0.01, -20.0, 12.0);
+        }
+
+        assert!((gain - 1.0).abs() < 1e-6, "Expected gain 1.0, got {}", gain);
+    }
+
+    #[test]
+    fn test_ducker_limits_attenuation_to_amount() {
+        let mut ducker = Ducker::new();
+        ducker.set_times(0.1, 100.0, 44100.0);
+
+        let mut gain = 1.0;
+        for _ in 0..1000 {
+            gain = ducker.process(1.0, -40.0, 12.0);
+        }
+
+        let expected = 10.0f32.powf(-12.0 / 20.0);
+        assert!(
+            (gain - expected).abs() < 1e-4,
+            "Expected gain {}, got {}",
+            expected,
+            gain
+        );
+    }
+}
@@ -0,0 +1,70 @@
+/// Length of the loop held while the delay is frozen.
+///
+/// The length is latched when freeze engages and kept until it has faded out again, so taps,
+/// notes and delay time automation can't chop or repitch the held buffer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrozenLoop {
+    loop_samples: Option<usize>,
+}
+
+impl FrozenLoop {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the latched length.
+    pub fn reset(&mut self) {
+        self.loop_samples = None;
+    }
+
+    /// Return the loop length for the current freeze amount, latching `delay_samples` as soon as
+    /// the freeze starts fading in.
+    pub fn loop_samples(&mut self, freeze: f32, delay_samples: f32) -> usize {
+        if freeze <= 0.0 {
+            self.loop_samples = None;
+            return delay_samples.round() as usize;
+        }
+
+        *self
+            .loop_samples
+            .get_or_insert(delay_samples.round() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::DelayLine;
+
+    #[test]
+    fn test_frozen_loop_ignores_delay_time_changes() {
+        let mut delay = DelayLine::new(100.0, 1000.0);
+        let mut frozen_loop = FrozenLoop::new();
+
+        for n in 0..50 {
+            delay.write((n as f32 * 0.3).sin());
+        }
+        let contents =
+            |delay: &DelayLine| (1..=20).map(|n| delay.read(n as f32)).collect::<Vec<_>>();
+
+        // Freeze with a 10 sample loop, then move the delay time every few samples
+        let mut delay_samples = 10.0;
+        for _ in 0..10 {
+            delay.write_held(0.0, frozen_loop.loop_samples(1.0, delay_samples), 1.0);
+        }
+        let before = contents(&delay);
+        for n in 0..1000 {
+            if n % 7 == 0 {
+                delay_samples = 5.0 + (n % 30) as f32;
+            }
+            delay.write_held(0.0, frozen_loop.loop_samples(1.0, delay_samples), 1.0);
+        }
+
+        // Whole loops later the buffer holds exactly what it did
+        assert_eq!(contents(&delay), before);
+
+        // Releasing the freeze lets the loop follow the delay time again
+        assert_eq!(frozen_loop.loop_samples(0.0, 25.0), 25);
+        assert_eq!(frozen_loop.loop_samples(0.5, 30.0), 30);
+    }
+}
@@ -0,0 +1,138 @@
+use grimoire_dsp::svf::Svf;
+
+use crate::parameters::AntiAlias;
+
+/// Bit depth at which the quantiser is bypassed.
+pub const MAX_BIT_DEPTH: f32 = 24.0;
+
+/// Anti-aliasing cutoff as a fraction of the reduced sample rate, just below its Nyquist.
+const ANTI_ALIAS_CUTOFF: f32 = 0.45;
+
+/// Q of each stage of the Butterworth anti-aliasing filters.
+const GENTLE_Q: [f32; 1] = [std::f32::consts::FRAC_1_SQRT_2];
+const STEEP_Q: [f32; 2] = [0.541_196_1, 1.306_563];
+
+/// Emulates a cheap digital converter by reducing the sample rate and bit depth.
+///
+/// The input is band limited by an optional low-pass, sampled and held at the reduced rate and
+/// then quantised. Without the anti-aliasing filter everything above the reduced Nyquist folds
+/// back as inharmonic aliasing.
+#[derive(Clone, Copy, Debug)]
+pub struct LoFi {
+    filters: [Svf; 2],
+    stages: usize,
+    /// Reduced rate relative to the host rate, `1.0` disables the sample and hold
+    rate_ratio: f32,
+    phase: f32,
+    held: f32,
+    sample_rate: f32,
+}
+
+impl LoFi {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            filters: [Svf::new(); 2],
+            stages: 0,
+            rate_ratio: 1.0,
+            phase: 0.0,
+            held: 0.0,
+            sample_rate,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        self.phase = 0.0;
+        self.held = 0.0;
+    }
+
+    /// Set the reduced sample rate and the anti-aliasing filter in front of the sample and hold.
+    /// Rates at or above the host sample rate disable the decimation.
+    pub fn set_rate(&mut self, rate_hz: f32, anti_alias: AntiAlias) {
+        self.rate_ratio = (rate_hz / self.sample_rate).min(1.0);
+
+        let stage_q: &[f32] = match anti_alias {
+            AntiAlias::Off => &[],
+            AntiAlias::Gentle => &GENTLE_Q,
+            AntiAlias::Steep => &STEEP_Q,
+        };
+        self.stages = stage_q.len();
+        for (filter, q) in self.filters.iter_mut().zip(stage_q) {
+            filter.update(rate_hz * ANTI_ALIAS_CUTOFF, *q, self.sample_rate);
+        }
+    }
+
+    /// Process a single sample, quantising it to `bit_depth` bits.
+    pub fn process(&mut self, input: f32, bit_depth: f32) -> f32 {
+        let mut output = input;
+
+        if self.rate_ratio < 1.0 {
+            let filtered = self.filters[..self.stages]
+                .iter_mut()
+                .fold(input, |sample, filter| filter.process(sample).low);
+
+            self.phase += self.rate_ratio;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+                self.held = filtered;
+            }
+            output = self.held;
+        }
+
+        if bit_depth < MAX_BIT_DEPTH {
+            output = quantise(output, bit_depth);
+        }
+
+        output
+    }
+}
+
+/// Round a sample to the nearest of the `2^(bits - 1)` steps per polarity.
+fn quantise(sample: f32, bit_depth: f32) -> f32 {
+    let steps = 2.0f32.powf(bit_depth - 1.0);
+    (sample * steps).round() / steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lofi_quantises_to_bit_depth() {
+        let mut lofi = LoFi::new(44100.0);
+        lofi.set_rate(44100.0, AntiAlias::Off);
+
+        // 3 bits leaves steps of 0.25
+        assert_eq!(lofi.process(0.3, 3.0), 0.25);
+        assert_eq!(lofi.process(-0.6, 3.0), -0.5);
+
+        // The top of the range is bypassed
+        assert_eq!(lofi.process(0.3, MAX_BIT_DEPTH), 0.3);
+    }
+
+    #[test]
+    fn test_lofi_holds_samples_at_reduced_rate() {
+        let mut lofi = LoFi::new(48000.0);
+        lofi.set_rate(12000.0, AntiAlias::Off);
+
+        let mut changes = 0;
+        let mut previous = 0.0;
+        for i in 0..4800 {
+            let output = lofi.process(i as f32, MAX_BIT_DEPTH);
+            if output != previous {
+                changes += 1;
+                previous = output;
+            }
+        }
+
+        // A quarter of the host rate updates on every fourth sample
+        assert_eq!(changes, 1200);
+    }
+}
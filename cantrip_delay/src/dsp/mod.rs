@@ -1,3 +1,23 @@
+mod bbd;
 mod delay_line;
+mod diffuser;
+mod diffusion;
+mod ducker;
+mod frozen_loop;
+mod lofi;
+mod noise;
+mod pitch_shifter;
+mod reverse;
+mod tap_tempo;
 
+pub use bbd::Bbd;
 pub use delay_line::DelayLine;
+pub use diffuser::{Diffuser, DIFFUSER_MAX_LATENCY_MS};
+pub use diffusion::Diffusion;
+pub use ducker::Ducker;
+pub use frozen_loop::FrozenLoop;
+pub use lofi::LoFi;
+pub use noise::{Noise, NOISE_LEVEL};
+pub use pitch_shifter::PitchShifter;
+pub use reverse::{ReverseReader, REVERSE_FADE_MS};
+pub use tap_tempo::TapTempo;
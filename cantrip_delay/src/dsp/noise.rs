@@ -0,0 +1,143 @@
+use std::f32::consts::TAU;
+
+use crate::parameters::NoiseType;
+
+/// Output level of the noise source at 100%, roughly -30 dBFS.
+pub const NOISE_LEVEL: f32 = 0.03;
+
+/// Cutoff of the low-pass that takes the harshest top end off the tape hiss.
+const HISS_CUTOFF_HZ: f32 = 8000.0;
+
+/// Cutoff of the low-pass shaping the rumble under the vinyl crackle.
+const RUMBLE_CUTOFF_HZ: f32 = 200.0;
+
+/// Average number of crackles per second.
+const CRACKLE_DENSITY: f32 = 10.0;
+
+/// Decay time of a single crackle in milliseconds.
+const CRACKLE_DECAY_MS: f32 = 0.5;
+
+/// Tape hiss and vinyl crackle generator.
+///
+/// White noise comes from a xorshift generator, so every instance needs its own seed to keep
+/// the channels decorrelated.
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    state: u32,
+    hiss_coeff: f32,
+    rumble_coeff: f32,
+    crackle_coeff: f32,
+    crackle_chance: f32,
+    hiss: f32,
+    rumble: f32,
+    crackle: f32,
+}
+
+impl Noise {
+    pub fn new(seed: u32, sample_rate: f32) -> Self {
+        let mut noise = Self {
+            // Xorshift gets stuck at zero
+            state: seed.max(1),
+            hiss_coeff: 0.0,
+            rumble_coeff: 0.0,
+            crackle_coeff: 0.0,
+            crackle_chance: 0.0,
+            hiss: 0.0,
+            rumble: 0.0,
+            crackle: 0.0,
+        };
+        noise.set_sample_rate(sample_rate);
+        noise
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.hiss_coeff = one_pole_coeff(HISS_CUTOFF_HZ, sample_rate);
+        self.rumble_coeff = one_pole_coeff(RUMBLE_CUTOFF_HZ, sample_rate);
+        self.crackle_coeff = (-1.0 / (CRACKLE_DECAY_MS * 0.001 * sample_rate)).exp();
+        self.crackle_chance = CRACKLE_DENSITY / sample_rate;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.hiss = 0.0;
+        self.rumble = 0.0;
+        self.crackle = 0.0;
+    }
+
+    /// Generate the next noise sample, peaking around ±1.
+    pub fn next(&mut self, noise_type: NoiseType) -> f32 {
+        let white = self.white();
+
+        match noise_type {
+            NoiseType::Tape => {
+                self.hiss += (white - self.hiss) * self.hiss_coeff;
+                self.hiss
+            }
+            NoiseType::Vinyl => {
+                self.rumble += (white - self.rumble) * self.rumble_coeff;
+
+                // Crackles are random clicks that die away within a millisecond
+                if self.unipolar() < self.crackle_chance {
+                    self.crackle = self.white();
+                }
+                self.crackle *= self.crackle_coeff;
+                if self.crackle.abs() < 1e-15 {
+                    self.crackle = 0.0;
+                }
+
+                self.crackle + self.rumble
+            }
+        }
+    }
+
+    /// Uniform random number in the `[0, 1)` range.
+    fn unipolar(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Uniform random number in the `[-1, 1)` range.
+    fn white(&mut self) -> f32 {
+        self.unipolar() * 2.0 - 1.0
+    }
+}
+
+/// Coefficient of a one-pole low-pass with the given cutoff.
+fn one_pole_coeff(cutoff_hz: f32, sample_rate: f32) -> f32 {
+    1.0 - (-TAU * cutoff_hz / sample_rate).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_is_bounded_and_not_silent() {
+        for noise_type in [NoiseType::Tape, NoiseType::Vinyl] {
+            let mut noise = Noise::new(1, 44100.0);
+
+            let mut peak = 0.0f32;
+            for _ in 0..44100 {
+                peak = peak.max(noise.next(noise_type).abs());
+            }
+
+            assert!(
+                peak > 0.1 && peak <= 1.0,
+                "Unexpected peak {} for {:?}",
+                peak,
+                noise_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_noise_seeds_are_decorrelated() {
+        let mut left = Noise::new(1, 44100.0);
+        let mut right = Noise::new(2, 44100.0);
+
+        let differs = (0..100).any(|_| left.next(NoiseType::Tape) != right.next(NoiseType::Tape));
+        assert!(differs);
+    }
+}
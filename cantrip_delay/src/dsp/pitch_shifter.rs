@@ -0,0 +1,103 @@
+use std::f32::consts::PI;
+
+use super::DelayLine;
+
+/// Length of the window the read heads sweep through in milliseconds.
+const WINDOW_MS: f32 = 50.0;
+
+/// Delay line based pitch shifter.
+///
+/// Two read heads move through a short window at a speed set by the pitch ratio, half a window
+/// apart. Each head is faded in and out with a sine squared window so one is always silent when it
+/// wraps around, and the two windows always sum to one.
+pub struct PitchShifter {
+    buffer: DelayLine,
+    phase: f32,
+}
+
+impl PitchShifter {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            // One extra millisecond for the read heads' minimum delay
+            buffer: DelayLine::new(WINDOW_MS + 1.0, sample_rate),
+            phase: 0.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.buffer.set_sample_rate(sample_rate, WINDOW_MS + 1.0);
+        self.phase = 0.0;
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.reset();
+        self.phase = 0.0;
+    }
+
+    /// Process a single sample, shifting it by `semitones`.
+    pub fn process(&mut self, input: f32, semitones: f32) -> f32 {
+        let ratio = 2.0f32.powf(semitones / 12.0);
+        let window = self.buffer.ms_to_samples(WINDOW_MS);
+
+        // Reading `ratio` samples per written sample means the delay changes by `1 - ratio`
+        self.phase += (1.0 - ratio) / window;
+        self.phase -= self.phase.floor();
+
+        let mut output = 0.0;
+        for head_phase in [self.phase, (self.phase + 0.5) % 1.0] {
+            let gain = (PI * head_phase).sin().powi(2);
+            output += self.buffer.read(1.0 + head_phase * window) * gain;
+        }
+
+        self.buffer.write(input);
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zero_crossings(semitones: f32) -> usize {
+        let sample_rate = 44100.0;
+        let mut shifter = PitchShifter::new(sample_rate);
+
+        let mut crossings = 0;
+        let mut previous = 0.0;
+        for n in 0..44100 {
+            let input = (2.0 * PI * 220.0 * n as f32 / sample_rate).sin();
+            let output = shifter.process(input, semitones);
+
+            // Skip the first window while the buffer fills up
+            if n > 4410 && (previous < 0.0) != (output < 0.0) {
+                crossings += 1;
+            }
+            previous = output;
+        }
+
+        crossings
+    }
+
+    #[test]
+    fn test_pitch_shifter_unison() {
+        // 220 Hz has 396 zero crossings in 0.9 seconds
+        let crossings = zero_crossings(0.0);
+        assert!((crossings as i32 - 396).abs() <= 2, "Got {} crossings", crossings);
+    }
+
+    #[test]
+    fn test_pitch_shifter_octaves() {
+        // The crossfades between the heads add a few extra crossings
+        for (semitones, expected) in [(12.0, 792.0), (-12.0, 198.0)] {
+            let crossings = zero_crossings(semitones) as f32;
+            assert!(
+                (crossings / expected - 1.0).abs() < 0.1,
+                "Expected ~{} crossings at {} semitones, got {}",
+                expected,
+                semitones,
+                crossings
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::f32::consts::FRAC_PI_2;
+
+use super::DelayLine;
+
+/// Longest crossfade between two reversed chunks in milliseconds.
+pub const REVERSE_FADE_MS: f32 = 10.0;
+
+/// Reads a [`DelayLine`] backwards, one chunk at a time.
+///
+/// At the start of every chunk the most recent `chunk_len` samples are played back in reverse.
+/// While the next chunk fades in, the previous one keeps playing backwards past its end and fades
+/// out, so there is neither a click nor a gap at the boundary. This needs the delay line to hold
+/// `2 * (chunk_len + fade_len)` samples.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReverseReader {
+    chunk_len: f32,
+    previous_len: f32,
+    position: f32,
+}
+
+impl ReverseReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the reader to the start of a chunk.
+    pub fn reset(&mut self) {
+        self.chunk_len = 0.0;
+        self.previous_len = 0.0;
+        self.position = 0.0;
+    }
+
+    /// Read the next reversed sample.
+    ///
+    /// This should be called once per sample before writing to the delay line. `chunk_samples` is
+    /// only picked up at the start of a chunk, so changing it never causes a jump in the middle of
+    /// one.
+    pub fn read(&mut self, delay_line: &DelayLine, chunk_samples: f32) -> f32 {
+        if self.position >= self.chunk_len {
+            self.previous_len = self.chunk_len;
+            self.chunk_len = chunk_samples.max(2.0).round();
+            self.position = 0.0;
+        }
+
+        // The write head moves one sample forward for every sample the read head moves back
+        let current = delay_line.read(2.0 * self.position + 1.0);
+
+        let fade_len = delay_line
+            .ms_to_samples(REVERSE_FADE_MS)
+            .min(self.chunk_len / 2.0);
+        let output = if self.position < fade_len {
+            let previous = delay_line.read(2.0 * (self.previous_len + self.position) + 1.0);
+            let fade = self.position / fade_len * FRAC_PI_2;
+
+            // Equal power, as the two chunks are unrelated parts of the signal
+            current * fade.sin() + previous * fade.cos()
+        } else {
+            current
+        };
+
+        self.position += 1.0;
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_reader_plays_chunks_backwards() {
+        // 10 sample chunks with a 5 sample crossfade
+        let mut delay = DelayLine::new(100.0, 1000.0);
+        let mut reader = ReverseReader::new();
+
+        for n in 0..40 {
+            let output = reader.read(&delay, 10.0);
+            delay.write(n as f32);
+
+            // After the crossfade each chunk mirrors the 10 samples before it started
+            let position = n % 10;
+            if n >= 10 && position >= 5 {
+                let chunk_start = n - position;
+                assert_eq!(output, (chunk_start - 1 - position) as f32);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+/// Turns taps into a delay time.
+///
+/// Every tap that follows the previous one within the timeout extends the current sequence, and
+/// the delay time is the average interval over the whole sequence. A longer pause starts a new
+/// sequence, so a single stray tap never changes the delay time on its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TapTempo {
+    /// Number of taps in the current sequence
+    taps: u32,
+    /// Samples since the last tap
+    since_last: u32,
+    /// Samples since the first tap of the current sequence
+    since_first: u32,
+    /// Whether the momentary trigger is held down
+    pressed: bool,
+}
+
+impl TapTempo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the current sequence.
+    pub fn reset(&mut self) {
+        self.taps = 0;
+        self.since_last = 0;
+        self.since_first = 0;
+    }
+
+    /// Let `samples` samples pass.
+    pub fn advance(&mut self, samples: u32) {
+        self.since_last = self.since_last.saturating_add(samples);
+        self.since_first = self.since_first.saturating_add(samples);
+    }
+
+    /// Take over the state of the trigger without registering a tap, so a trigger that is
+    /// already held down doesn't count as a press.
+    pub fn set_pressed(&mut self, pressed: bool) {
+        self.pressed = pressed;
+    }
+
+    /// Follow the momentary trigger, registering a tap when it is pressed but not when it is
+    /// released. Returns the same as [`tap()`](Self::tap) on a press.
+    pub fn trigger(&mut self, pressed: bool, timeout_samples: u32) -> Option<f32> {
+        let was_pressed = std::mem::replace(&mut self.pressed, pressed);
+        if pressed && !was_pressed {
+            self.tap(timeout_samples)
+        } else {
+            None
+        }
+    }
+
+    /// Register a tap. Returns the average interval between taps in samples once there are at
+    /// least two taps in the sequence.
+    pub fn tap(&mut self, timeout_samples: u32) -> Option<f32> {
+        if self.taps == 0 || self.since_last > timeout_samples {
+            self.taps = 1;
+            self.since_first = 0;
+        } else {
+            self.taps += 1;
+        }
+        self.since_last = 0;
+
+        if self.taps > 1 {
+            Some(self.since_first as f32 / (self.taps - 1) as f32)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tap_tempo_averages_intervals() {
+        let mut tap_tempo = TapTempo::new();
+
+        assert_eq!(tap_tempo.tap(1000), None);
+        tap_tempo.advance(400);
+        assert_eq!(tap_tempo.tap(1000), Some(400.0));
+        tap_tempo.advance(600);
+        assert_eq!(tap_tempo.tap(1000), Some(500.0));
+    }
+
+    #[test]
+    fn test_tap_tempo_restarts_after_timeout() {
+        let mut tap_tempo = TapTempo::new();
+
+        tap_tempo.tap(1000);
+        tap_tempo.advance(500);
+        tap_tempo.tap(1000);
+        tap_tempo.advance(1500);
+
+        // Too late to belong to the first sequence
+        assert_eq!(tap_tempo.tap(1000), None);
+        tap_tempo.advance(300);
+        assert_eq!(tap_tempo.tap(1000), Some(300.0));
+    }
+
+    #[test]
+    fn test_tap_tempo_counts_presses_not_releases() {
+        let mut tap_tempo = TapTempo::new();
+
+        // Held down for 100 samples, then pressed again 400 samples after the first press
+        assert_eq!(tap_tempo.trigger(true, 1000), None);
+        tap_tempo.advance(100);
+        assert_eq!(tap_tempo.trigger(false, 1000), None);
+        tap_tempo.advance(300);
+        assert_eq!(tap_tempo.trigger(true, 1000), Some(400.0));
+        tap_tempo.advance(100);
+        assert_eq!(tap_tempo.trigger(false, 1000), None);
+    }
+
+    #[test]
+    fn test_tap_tempo_ignores_trigger_held_at_start() {
+        let mut tap_tempo = TapTempo::new();
+
+        // A session saved with the trigger down doesn't start a sequence
+        tap_tempo.set_pressed(true);
+        assert_eq!(tap_tempo.trigger(true, 1000), None);
+        tap_tempo.advance(200);
+        tap_tempo.trigger(false, 1000);
+        tap_tempo.advance(200);
+        assert_eq!(tap_tempo.trigger(true, 1000), None);
+        tap_tempo.advance(300);
+        tap_tempo.trigger(false, 1000);
+        assert_eq!(tap_tempo.trigger(true, 1000), Some(300.0));
+    }
+}
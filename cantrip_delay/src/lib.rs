@@ -6,14 +6,47 @@ mod dsp;
 mod parameters;
 
 use constants::*;
-use dsp::DelayLine;
-use parameters::DelayParams;
+use dsp::{
+    Bbd, DelayLine, Diffusion, Ducker, FrozenLoop, LoFi, Noise, PitchShifter, ReverseReader,
+    TapTempo, NOISE_LEVEL, REVERSE_FADE_MS,
+};
+use grimoire_dsp::lfo::Lfo;
+use parameters::{DelayMode, DelayParams};
 
 const MAX_DELAY_MS: f32 = 2000.0;
+/// Reverse mode reads up to twice the delay time plus a crossfade back.
+const BUFFER_MS: f32 = 2.0 * (MAX_DELAY_MS + REVERSE_FADE_MS);
+const FREEZE_FADE_MS: f32 = 50.0;
+/// The top of the lo-fi rate range turns decimation off, whatever the host sample rate.
+const LOFI_RATE_OFF_HZ: f32 = 48000.0;
 
 struct CantripDelay {
     params: Arc<DelayParams>,
     delay_lines: [DelayLine; 2],
+    reverse_readers: [ReverseReader; 2],
+    diffusion: [Diffusion; 2],
+    pitch_shifters: [PitchShifter; 2],
+    lofi: [LoFi; 2],
+    noise: [Noise; 2],
+    bbds: [Bbd; 2],
+    /// One LFO per channel, the second one shifted by the stereo phase
+    lfos: [Lfo; 2],
+    /// Crossfade between normal operation (0.0) and the frozen loop (1.0)
+    freeze_fade: Smoother<f32>,
+    frozen: bool,
+    frozen_loop: FrozenLoop,
+    ducker: Ducker,
+    tap_tempo: TapTempo,
+    /// Delay time set by tap tempo or a MIDI note, used instead of the Delay Time parameter. Once
+    /// released, the override smoother glides back to the parameter before handing it control.
+    delay_override: Option<f32>,
+    override_smoother: Smoother<f32>,
+    /// The note that set the delay time, releasing it hands control back to the parameter
+    held_note: Option<u8>,
+    /// Last seen Delay Time parameter value, moving the parameter cancels the override
+    last_delay_time: f32,
+    /// Whether the mono input needs to be copied to the second output channel
+    mono_to_stereo: bool,
     sample_rate: f32,
 }
 
@@ -22,9 +55,27 @@ impl Default for CantripDelay {
         Self {
             params: Arc::new(DelayParams::default()),
             delay_lines: [
-                DelayLine::new(MAX_DELAY_MS, 44100.0),
-                DelayLine::new(MAX_DELAY_MS, 44100.0),
+                DelayLine::new(BUFFER_MS, 44100.0),
+                DelayLine::new(BUFFER_MS, 44100.0),
             ],
+            reverse_readers: [ReverseReader::new(); 2],
+            diffusion: [Diffusion::new(44100.0), Diffusion::new(44100.0)],
+            pitch_shifters: [PitchShifter::new(44100.0), PitchShifter::new(44100.0)],
+            lofi: [LoFi::new(44100.0); 2],
+            // Different seeds keep the noise of the two channels apart
+            noise: [Noise::new(1, 44100.0), Noise::new(2, 44100.0)],
+            bbds: [Bbd::new(3, 44100.0), Bbd::new(4, 44100.0)],
+            lfos: [Lfo::new(); 2],
+            freeze_fade: Smoother::new(SmoothingStyle::Linear(FREEZE_FADE_MS)),
+            frozen: false,
+            frozen_loop: FrozenLoop::new(),
+            ducker: Ducker::new(),
+            tap_tempo: TapTempo::new(),
+            delay_override: None,
+            override_smoother: Smoother::new(SmoothingStyle::Linear(50.0)),
+            held_note: None,
+            last_delay_time: 0.0,
+            mono_to_stereo: false,
             sample_rate: 44100.0,
         }
     }
@@ -37,15 +88,31 @@ impl Plugin for CantripDelay {
     const EMAIL: &'static str = EMAIL;
     const VERSION: &'static str = VERSION;
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
-        aux_input_ports: &[],
-        aux_output_ports: &[],
-        names: PortNames::const_default(),
-    }];
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+    ];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
@@ -58,14 +125,33 @@ impl Plugin for CantripDelay {
 
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
+        audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
+        self.mono_to_stereo = audio_io_layout.main_input_channels == NonZeroU32::new(1)
+            && audio_io_layout.main_output_channels == NonZeroU32::new(2);
         self.sample_rate = buffer_config.sample_rate;
         for delay_line in &mut self.delay_lines {
-            delay_line.set_sample_rate(buffer_config.sample_rate, MAX_DELAY_MS);
+            delay_line.set_sample_rate(buffer_config.sample_rate, BUFFER_MS);
         }
+        for diffusion in &mut self.diffusion {
+            diffusion.set_sample_rate(buffer_config.sample_rate);
+        }
+        for pitch_shifter in &mut self.pitch_shifters {
+            pitch_shifter.set_sample_rate(buffer_config.sample_rate);
+        }
+        for lofi in &mut self.lofi {
+            lofi.set_sample_rate(buffer_config.sample_rate);
+        }
+        for noise in &mut self.noise {
+            noise.set_sample_rate(buffer_config.sample_rate);
+        }
+        for bbd in &mut self.bbds {
+            bbd.set_sample_rate(buffer_config.sample_rate);
+        }
+        // A trigger restored in its pressed state isn't a tap
+        self.tap_tempo.set_pressed(self.params.tap.value());
         true
     }
 
@@ -73,24 +159,235 @@ impl Plugin for CantripDelay {
         for delay_line in &mut self.delay_lines {
             delay_line.reset();
         }
+        for reverse_reader in &mut self.reverse_readers {
+            reverse_reader.reset();
+        }
+        for diffusion in &mut self.diffusion {
+            diffusion.reset();
+        }
+        for pitch_shifter in &mut self.pitch_shifters {
+            pitch_shifter.reset();
+        }
+        for lofi in &mut self.lofi {
+            lofi.reset();
+        }
+        for noise in &mut self.noise {
+            noise.reset();
+        }
+        for bbd in &mut self.bbds {
+            bbd.reset();
+        }
+        for lfo in &mut self.lfos {
+            lfo.reset();
+        }
+
+        self.frozen = self.params.freeze.value();
+        self.freeze_fade.reset(if self.frozen { 1.0 } else { 0.0 });
+        self.frozen_loop.reset();
+
+        self.ducker.reset();
+
+        self.tap_tempo.reset();
+        self.tap_tempo.set_pressed(self.params.tap.value());
+        self.delay_override = None;
+        self.override_smoother.reset(self.params.delay_time.value());
+        self.held_note = None;
+        self.last_delay_time = self.params.delay_time.value();
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for mut channel_samples in buffer.iter_samples() {
+        let mode = self.params.mode.value();
+
+        // Falls back to the free-running rate if the host doesn't report a tempo
+        let synced_rate = if self.params.mod_sync.value() {
+            let division = self.params.mod_division.value();
+            context
+                .transport()
+                .tempo
+                .map(|tempo| division.rate_hz(tempo as f32))
+        } else {
+            None
+        };
+
+        // Touching the Delay Time parameter takes back control from taps and notes
+        let delay_time_param = self.params.delay_time.value();
+        if delay_time_param != self.last_delay_time {
+            self.last_delay_time = delay_time_param;
+            self.release_delay_time();
+        }
+
+        let frozen = self.params.freeze.value();
+        if frozen != self.frozen {
+            self.frozen = frozen;
+            self.freeze_fade
+                .set_target(self.sample_rate, if frozen { 1.0 } else { 0.0 });
+        }
+
+        let diffusion_position = self.params.diffusion_position.value();
+        for diffusion in &mut self.diffusion {
+            diffusion.set_position(diffusion_position);
+        }
+        let shimmer_pitch = self.params.shimmer_pitch.value();
+
+        let bit_depth = self.params.bit_depth.value();
+        let lofi_rate = match self.params.lofi_rate.value() {
+            rate if rate >= LOFI_RATE_OFF_HZ => self.sample_rate,
+            rate => rate,
+        };
+        let anti_alias = self.params.anti_alias.value();
+        for lofi in &mut self.lofi {
+            lofi.set_rate(lofi_rate, anti_alias);
+        }
+        let noise_type = self.params.noise_type.value();
+
+        let duck_amount = self.params.duck_amount.value();
+        let duck_threshold = self.params.duck_threshold.value();
+        self.ducker.set_times(
+            self.params.duck_attack.value(),
+            self.params.duck_release.value(),
+            self.sample_rate,
+        );
+
+        let mut next_event = context.next_event();
+        for (sample_id, mut channel_samples) in buffer.iter_samples().enumerate() {
+            while let Some(event) = next_event {
+                if event.timing() > sample_id as u32 {
+                    break;
+                }
+
+                match event {
+                    NoteEvent::NoteOn { note, .. } => {
+                        // A delay of one period makes the feedback loop resonate at the note
+                        let period_ms = 1000.0 / util::midi_note_to_freq(note);
+                        self.override_delay_time(period_ms, false);
+                        self.held_note = Some(note);
+                    }
+                    NoteEvent::NoteOff { note, .. } if self.held_note == Some(note) => {
+                        self.release_delay_time();
+                    }
+                    _ => (),
+                }
+
+                next_event = context.next_event();
+            }
+
+            if self.mono_to_stereo {
+                // Only the first channel holds input, the second one is ours to fill
+                let mono = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+                if let Some(right) = channel_samples.get_mut(1) {
+                    *right = mono;
+                }
+            }
+
+            // Taps are timestamped per sample so the interval doesn't jitter with the block size
+            self.tap(self.params.tap.value());
+            self.tap_tempo.advance(1);
+
             let delay_time = self.params.delay_time.smoothed.next();
+            let delay_time =
+                if self.delay_override.is_some() || self.override_smoother.is_smoothing() {
+                    self.override_smoother.next()
+                } else {
+                    delay_time
+                };
             let feedback = self.params.feedback.smoothed.next() / 100.0;
             let mix = self.params.mix.smoothed.next() / 100.0;
+            let mod_rate = self.params.mod_rate.smoothed.next();
+            let mod_depth = self.params.mod_depth.smoothed.next() / 100.0;
+            let stereo_phase = self.params.stereo_phase.smoothed.next() / 360.0;
+            let diffusion = self.params.diffusion.smoothed.next() / 100.0;
+
+            // The modulated modes rely on short, exact delay times
+            let diffusion = if mode.is_modulated() { 0.0 } else { diffusion };
+            let shimmer = self.params.shimmer.smoothed.next() / 100.0;
+            let noise = self.params.noise.smoothed.next() / 100.0 * NOISE_LEVEL;
+
+            // Vibrato is a pure pitch modulation, so only the wet signal is heard
+            let (feedback, mix) = match mode {
+                DelayMode::Vibrato => (0.0, 1.0),
+                _ => (feedback, mix),
+            };
+
+            // While frozen the input is faded out and the buffer loops unchanged. Fading rather
+            // than switching also crossfades the seam of the loop.
+            let freeze = self.freeze_fade.next();
+            let input_gain = 1.0 - freeze;
+            let centre_ms = if mode.is_modulated() {
+                mode.centre_ms()
+            } else {
+                delay_time
+            };
+            let loop_samples = self
+                .frozen_loop
+                .loop_samples(freeze, self.delay_lines[0].ms_to_samples(centre_ms));
+
+            // The ducker follows the loudest channel so the stereo image stays put
+            let input_peak = channel_samples
+                .iter_mut()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let duck_gain = self.ducker.process(input_peak, duck_threshold, duck_amount);
+
+            for (channel_idx, lfo) in self.lfos.iter_mut().enumerate() {
+                lfo.set_rate(synced_rate.unwrap_or(mod_rate), self.sample_rate);
+                lfo.set_phase_offset(stereo_phase * channel_idx as f32);
+            }
 
             for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
                 let dry = *sample;
-                let wet = self.delay_lines[channel_idx].process(dry, delay_time, feedback);
+                let delay_line = &mut self.delay_lines[channel_idx];
+                let diffusion_stage = &mut self.diffusion[channel_idx];
+                diffusion_stage.set_amount(diffusion);
+                let lfo = self.lfos[channel_idx].process();
+
+                let input = diffusion_stage.process_input(dry * input_gain);
+
+                let mut wet = match mode {
+                    DelayMode::Reverse => {
+                        let chunk_samples = delay_line.ms_to_samples(delay_time);
+                        let wet = self.reverse_readers[channel_idx].read(delay_line, chunk_samples);
+                        diffusion_stage.process_uncompensated(wet)
+                    }
+                    _ => {
+                        let delay_ms = centre_ms + lfo * mod_depth * mode.max_depth_ms();
+                        diffusion_stage.read(delay_line, delay_line.ms_to_samples(delay_ms))
+                    }
+                };
+
+                let bbd = &mut self.bbds[channel_idx];
+                if mode == DelayMode::Bbd {
+                    // The clock follows the delay time, not the wobble on top of it
+                    bbd.set_delay(delay_time);
+                    wet = bbd.decode(wet);
+                }
+
+                // Degrading the repeats inside the loop makes every one sound cheaper
+                wet = self.lofi[channel_idx].process(wet, bit_depth);
+
+                // Only the repeats are shifted, so every one climbs or falls further in pitch
+                let shifted = self.pitch_shifters[channel_idx].process(wet, shimmer_pitch);
+                let feedback_signal =
+                    diffusion_stage.process_feedback(wet + (shifted - wet) * shimmer);
 
-                let mut output = dry * (1.0 - mix) + wet * mix;
+                let mut delay_input = input + feedback_signal * feedback;
+                if mode == DelayMode::Bbd {
+                    delay_input = bbd.encode(delay_input);
+                }
+
+                // The frozen loop skips the BBD, lo-fi, diffusion and shimmer stages, which would
+                // otherwise wear it down a little more on every pass
+                delay_line.write_held(delay_input, loop_samples, freeze);
+
+                // The noise stays out of the loop so it can't build up while frozen
+                if noise > 0.0 {
+                    wet += self.noise[channel_idx].next(noise_type) * noise;
+                }
+
+                let mut output = dry * (1.0 - mix) + wet * duck_gain * mix;
 
                 if output.abs() < 1e-15 {
                     output = 0.0;
@@ -104,6 +401,44 @@ impl Plugin for CantripDelay {
     }
 }
 
+impl CantripDelay {
+    /// Take the delay time over from the parameter, gliding to it or jumping straight there.
+    fn override_delay_time(&mut self, delay_ms: f32, glide: bool) {
+        if glide {
+            // Glides start from wherever the delay time is, which may be halfway back from an
+            // earlier override
+            if self.delay_override.is_none() && !self.override_smoother.is_smoothing() {
+                let current = self.params.delay_time.smoothed.previous_value();
+                self.override_smoother.reset(current);
+            }
+            self.override_smoother
+                .set_target(self.sample_rate, delay_ms);
+        } else {
+            self.override_smoother.reset(delay_ms);
+        }
+        self.delay_override = Some(delay_ms);
+    }
+
+    /// Hand the delay time back to the parameter, gliding there so the jump doesn't click.
+    fn release_delay_time(&mut self) {
+        if self.delay_override.take().is_some() {
+            self.override_smoother
+                .set_target(self.sample_rate, self.params.delay_time.value());
+        }
+        self.held_note = None;
+    }
+
+    /// Follow the tap trigger, overriding the delay time once there are enough taps to measure it.
+    fn tap(&mut self, pressed: bool) {
+        let timeout = (MAX_DELAY_MS * 0.001 * self.sample_rate) as u32;
+        if let Some(interval) = self.tap_tempo.trigger(pressed, timeout) {
+            let delay_ms = (interval / self.sample_rate * 1000.0).min(MAX_DELAY_MS);
+            self.override_delay_time(delay_ms, true);
+            self.held_note = None;
+        }
+    }
+}
+
 impl ClapPlugin for CantripDelay {
     const CLAP_ID: &'static str = CLAP_ID;
     const CLAP_DESCRIPTION: Option<&'static str> = CLAP_DESCRIPTION;
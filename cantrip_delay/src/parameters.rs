@@ -1,15 +1,170 @@
 use nih_plug::prelude::*;
 
+use grimoire_dsp::lfo::NoteDivision;
+
 #[derive(Params)]
 pub struct DelayParams {
     #[id = "time"]
     pub delay_time: FloatParam,
 
+    /// Momentary trigger, the delay time follows the interval between presses
+    #[id = "tap"]
+    pub tap: BoolParam,
+
     #[id = "feedback"]
     pub feedback: FloatParam,
 
     #[id = "mix"]
     pub mix: FloatParam,
+
+    #[id = "mode"]
+    pub mode: EnumParam<DelayMode>,
+
+    /// LFO rate used by the modulated modes when not synced to the host tempo
+    #[id = "mod_rate"]
+    pub mod_rate: FloatParam,
+
+    /// LFO depth as a percentage of the current mode's maximum sweep
+    #[id = "mod_depth"]
+    pub mod_depth: FloatParam,
+
+    /// Sync the LFO rate to the host tempo
+    #[id = "mod_sync"]
+    pub mod_sync: BoolParam,
+
+    /// Length of one LFO cycle when synced to the host tempo
+    #[id = "mod_division"]
+    pub mod_division: EnumParam<NoteDivision>,
+
+    /// LFO phase offset of the right channel relative to the left channel
+    #[id = "stereo_phase"]
+    pub stereo_phase: FloatParam,
+
+    /// Stop recording and loop the current contents of the delay line
+    #[id = "freeze"]
+    pub freeze: BoolParam,
+
+    /// Amount of all-pass diffusion applied to the echoes
+    #[id = "diffusion"]
+    pub diffusion: FloatParam,
+
+    /// Whether the diffuser smears the input once or every repeat
+    #[id = "diffusion_position"]
+    pub diffusion_position: EnumParam<DiffusionPosition>,
+
+    /// Pitch shift applied to the repeats in the feedback path
+    #[id = "shimmer_pitch"]
+    pub shimmer_pitch: FloatParam,
+
+    /// Balance between the plain and the pitch shifted feedback signal
+    #[id = "shimmer"]
+    pub shimmer: FloatParam,
+
+    /// Maximum attenuation of the wet signal while the input is playing (0 = off)
+    #[id = "duck_amount"]
+    pub duck_amount: FloatParam,
+
+    /// Input level above which the wet signal is ducked
+    #[id = "duck_threshold"]
+    pub duck_threshold: FloatParam,
+
+    /// Ducking attack time in milliseconds
+    #[id = "duck_attack"]
+    pub duck_attack: FloatParam,
+
+    /// Ducking release time in milliseconds
+    #[id = "duck_release"]
+    pub duck_release: FloatParam,
+
+    /// Bit depth of the repeats (24 = off)
+    #[id = "bit_depth"]
+    pub bit_depth: FloatParam,
+
+    /// Sample rate the repeats are decimated to (48 kHz = off)
+    #[id = "lofi_rate"]
+    pub lofi_rate: FloatParam,
+
+    /// Low-pass applied before decimating to keep aliasing down
+    #[id = "anti_alias"]
+    pub anti_alias: EnumParam<AntiAlias>,
+
+    /// Level of the noise added to the wet signal
+    #[id = "noise"]
+    pub noise: FloatParam,
+
+    #[id = "noise_type"]
+    pub noise_type: EnumParam<NoiseType>,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum DelayMode {
+    #[name = "Echo"]
+    Echo,
+    #[name = "Chorus"]
+    Chorus,
+    #[name = "Flanger"]
+    Flanger,
+    #[name = "Vibrato"]
+    Vibrato,
+    #[name = "Reverse"]
+    Reverse,
+    #[name = "BBD"]
+    Bbd,
+}
+
+impl DelayMode {
+    /// Centre of the modulated delay time in milliseconds.
+    pub fn centre_ms(self) -> f32 {
+        match self {
+            Self::Echo | Self::Reverse | Self::Bbd => 0.0,
+            Self::Chorus => 20.0,
+            Self::Flanger => 3.0,
+            Self::Vibrato => 6.0,
+        }
+    }
+
+    /// Maximum deviation from the centre delay time in milliseconds, reached at 100% depth.
+    pub fn max_depth_ms(self) -> f32 {
+        match self {
+            Self::Echo | Self::Reverse => 0.0,
+            Self::Chorus => 8.0,
+            Self::Flanger => 2.5,
+            Self::Vibrato => 5.0,
+            // Just enough wobble to sound like an unstable analog clock
+            Self::Bbd => 1.0,
+        }
+    }
+
+    /// Whether the delay time follows the LFO instead of the delay time parameter.
+    pub fn is_modulated(self) -> bool {
+        matches!(self, Self::Chorus | Self::Flanger | Self::Vibrato)
+    }
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum DiffusionPosition {
+    #[name = "Before Delay"]
+    Pre,
+    #[name = "In Feedback"]
+    Feedback,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum AntiAlias {
+    #[name = "Off"]
+    Off,
+    #[name = "12 dB/oct"]
+    Gentle,
+    #[name = "24 dB/oct"]
+    Steep,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum NoiseType {
+    #[name = "Tape"]
+    Tape,
+    #[name = "Vinyl"]
+    Vinyl,
 }
 
 impl Default for DelayParams {
@@ -28,6 +183,8 @@ impl Default for DelayParams {
             .with_smoother(SmoothingStyle::Linear(50.0))
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
+            tap: BoolParam::new("Tap Tempo", false),
+
             feedback: FloatParam::new(
                 "Feedback",
                 30.0,
@@ -44,6 +201,173 @@ impl Default for DelayParams {
                 .with_unit(" %")
                 .with_smoother(SmoothingStyle::Linear(50.0))
                 .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            mode: EnumParam::new("Mode", DelayMode::Echo),
+
+            mod_rate: FloatParam::new(
+                "Mod Rate",
+                0.5,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+
+            mod_depth: FloatParam::new(
+                "Mod Depth",
+                50.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" %")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            mod_sync: BoolParam::new("Mod Sync", false),
+
+            mod_division: EnumParam::new("Mod Division", NoteDivision::Bar),
+
+            stereo_phase: FloatParam::new(
+                "Stereo Phase",
+                90.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 180.0,
+                },
+            )
+            .with_unit("°")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            freeze: BoolParam::new("Freeze", false),
+
+            diffusion: FloatParam::new(
+                "Diffusion",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" %")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            diffusion_position: EnumParam::new("Diffusion Position", DiffusionPosition::Feedback),
+
+            shimmer_pitch: FloatParam::new(
+                "Shimmer Pitch",
+                12.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" st")
+            .with_step_size(1.0),
+
+            shimmer: FloatParam::new(
+                "Shimmer",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" %")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            duck_amount: FloatParam::new(
+                "Duck Amount",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 48.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_step_size(0.1),
+
+            duck_threshold: FloatParam::new(
+                "Duck Threshold",
+                -30.0,
+                FloatRange::Linear {
+                    min: -60.0,
+                    max: 0.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_step_size(0.1),
+
+            duck_attack: FloatParam::new(
+                "Duck Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(0.1),
+
+            duck_release: FloatParam::new(
+                "Duck Release",
+                250.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+
+            bit_depth: FloatParam::new(
+                "Bit Depth",
+                24.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" bit")
+            .with_step_size(1.0),
+
+            lofi_rate: FloatParam::new(
+                "Lo-Fi Rate",
+                48000.0,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: 48000.0,
+                    factor: FloatRange::skew_factor(-1.5),
+                },
+            )
+            .with_unit(" Hz")
+            .with_step_size(1.0),
+
+            anti_alias: EnumParam::new("Anti-Alias", AntiAlias::Steep),
+
+            noise: FloatParam::new(
+                "Noise",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" %")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            noise_type: EnumParam::new("Noise Type", NoiseType::Tape),
         }
     }
 }
@@ -0,0 +1,83 @@
+use grimoire_dsp::biquad::Biquad;
+use grimoire_dsp::coefficients::BiquadCoefficients;
+use grimoire_dsp::filter_type::{Design, FilterType};
+
+/// A single EQ band: one biquad per channel, all sharing the same coefficients.
+#[derive(Clone, Copy, Debug)]
+pub struct Band {
+    filters: [Biquad; 2],
+    /// Settings the current coefficients were computed for, used to skip redundant updates
+    settings: Option<(FilterType, Design, f32, f32, f32)>,
+    coefficients: BiquadCoefficients,
+    enabled: bool,
+}
+
+impl Band {
+    pub fn new() -> Self {
+        Self {
+            filters: [Biquad::new(); 2],
+            settings: None,
+            coefficients: BiquadCoefficients::unity(),
+            enabled: false,
+        }
+    }
+
+    /// Reset the filter state and force the coefficients to be recomputed on the next update.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        self.settings = None;
+    }
+
+    /// Update the band, recomputing the coefficients only if a setting has changed. Returns
+    /// whether the band's response has changed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(
+        &mut self,
+        enabled: bool,
+        filter_type: FilterType,
+        design: Design,
+        freq: f32,
+        q: f32,
+        gain_db: f32,
+        sample_rate: f32,
+    ) -> bool {
+        let toggled = enabled != self.enabled;
+        // A band that is switched back on starts from silence rather than stale state
+        if enabled && !self.enabled {
+            self.reset();
+        }
+        self.enabled = enabled;
+        if !enabled {
+            return toggled;
+        }
+
+        let settings = (filter_type, design, freq, q, gain_db);
+        if self.settings == Some(settings) {
+            return toggled;
+        }
+
+        self.settings = Some(settings);
+        self.coefficients =
+            filter_type.compute_coefficients_with(design, freq, q, gain_db, sample_rate);
+        for filter in &mut self.filters {
+            filter.set_coefficients(self.coefficients);
+        }
+        true
+    }
+
+    /// Coefficients of the band, or `None` while it's disabled.
+    pub fn coefficients(&self) -> Option<BiquadCoefficients> {
+        self.enabled.then_some(self.coefficients)
+    }
+
+    /// Process a single sample of the given channel. Disabled bands pass the input through.
+    pub fn process(&mut self, channel: usize, input: f32) -> f32 {
+        if self.enabled {
+            self.filters[channel].process(input)
+        } else {
+            input
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::f64::consts::TAU;
+
+use grimoire_dsp::coefficients::BiquadCoefficients;
+use grimoire_dsp::convolution::PartitionedConvolver;
+use grimoire_dsp::fft::{Complex, Fft};
+
+/// Number of points the magnitude response is sampled at. At 44.1kHz the bins are about 11Hz
+/// apart, so very narrow bands in the lowest octaves come out wider than their IIR versions.
+const DESIGN_SIZE: usize = 4096;
+
+/// Length of the symmetric kernel, odd so its centre falls on a sample.
+pub const KERNEL_LEN: usize = DESIGN_SIZE - 1;
+
+/// Block size of the convolution, a trade-off between latency and CPU usage.
+pub const BLOCK_SIZE: usize = 256;
+
+/// Number of bins of the magnitude response sampled per design step.
+const BINS_PER_STEP: usize = 16;
+
+/// Shortest time between two designs in milliseconds. While the bands are smoothing the curve
+/// changes on every sample, and redesigning it at every block would eat up the audio thread.
+pub const DESIGN_INTERVAL_MS: f32 = 50.0;
+
+/// Linear phase version of an EQ curve.
+///
+/// The magnitude response is sampled on an evenly spaced grid and turned into a zero phase
+/// impulse response by an inverse FFT. Delaying that by half its length and applying a window
+/// gives a symmetric FIR filter with the same magnitude response, but no phase shift beyond a
+/// constant delay. The FIR filter runs as a partitioned FFT convolution.
+///
+/// Designing the filter at once would take far longer than a sample, so the work is split into
+/// small steps that run one per sample. They all fit into one convolution block, and the new
+/// kernel takes over at the next block boundary.
+#[derive(Clone, Debug)]
+pub struct LinearPhase {
+    /// Sections of the curve being designed
+    curve: Vec<BiquadCoefficients>,
+    sample_rate: f32,
+    step: DesignStep,
+    fft: Fft,
+    spectrum: Vec<Complex>,
+    window: Vec<f32>,
+    kernel: Vec<f32>,
+    convolver: PartitionedConvolver,
+    /// Samples left until the next design is allowed
+    holdoff: u32,
+}
+
+/// The next piece of work of a design.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DesignStep {
+    Idle,
+    /// Sample the magnitude response, starting at this bin
+    Sample(usize),
+    /// Run this step of the inverse FFT
+    Transform(usize),
+    /// Centre and window the impulse response
+    Window,
+    /// Hand this partition of the kernel to the convolver
+    Partition(usize),
+}
+
+impl LinearPhase {
+    /// Create a linear phase filter for curves of up to `max_sections` biquads.
+    pub fn new(max_sections: usize) -> Self {
+        // Blackman window, its low sidelobes keep the stopbands of the pass filters deep
+        let window = (0..KERNEL_LEN)
+            .map(|n| {
+                let x = TAU * n as f64 / (KERNEL_LEN - 1) as f64;
+                (0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()) as f32
+            })
+            .collect();
+
+        Self {
+            curve: Vec::with_capacity(max_sections),
+            sample_rate: 44100.0,
+            step: DesignStep::Idle,
+            fft: Fft::new(DESIGN_SIZE),
+            spectrum: vec![Complex::ZERO; DESIGN_SIZE],
+            window,
+            kernel: vec![0.0; KERNEL_LEN],
+            convolver: PartitionedConvolver::new(BLOCK_SIZE, KERNEL_LEN),
+            holdoff: 0,
+        }
+    }
+
+    /// Latency in samples: the convolution's block plus the delay of the kernel's centre.
+    pub fn latency(&self) -> u32 {
+        self.convolver.latency() + (KERNEL_LEN / 2) as u32
+    }
+
+    /// Clear the signal history and abandon a design in progress. The current curve is kept.
+    pub fn reset(&mut self) {
+        self.convolver.reset();
+        self.step = DesignStep::Idle;
+        self.holdoff = 0;
+    }
+
+    /// Whether a new curve can be designed. A new design only takes effect at the start of the
+    /// next convolution block, and at most once every [`DESIGN_INTERVAL_MS`]. A curve that keeps
+    /// changing is followed in steps, and its final shape is designed once it settles.
+    pub fn is_ready(&self) -> bool {
+        self.step == DesignStep::Idle && !self.convolver.kernel_pending() && self.holdoff == 0
+    }
+
+    /// Start designing the FIR filter for the combined magnitude response of a chain of biquads.
+    /// The design runs over the next samples in [`process()`](Self::process).
+    pub fn design(
+        &mut self,
+        curve: impl IntoIterator<Item = BiquadCoefficients>,
+        sample_rate: f32,
+    ) {
+        self.curve.clear();
+        self.curve.extend(curve);
+        self.sample_rate = sample_rate;
+        self.step = DesignStep::Sample(0);
+        self.holdoff = (DESIGN_INTERVAL_MS * 0.001 * sample_rate) as u32;
+    }
+
+    /// Process one sample of each channel.
+    pub fn process(&mut self, left: f32, right: f32) -> [f32; 2] {
+        self.design_step();
+        self.holdoff = self.holdoff.saturating_sub(1);
+        self.convolver.process(left, right)
+    }
+
+    /// Run the next step of the design in progress, if any.
+    fn design_step(&mut self) {
+        self.step = match self.step {
+            DesignStep::Idle => DesignStep::Idle,
+            DesignStep::Sample(start) => {
+                let end = (start + BINS_PER_STEP).min(DESIGN_SIZE / 2 + 1);
+                for bin in start..end {
+                    let freq = bin as f32 * self.sample_rate / DESIGN_SIZE as f32;
+                    let magnitude: f32 = self
+                        .curve
+                        .iter()
+                        .map(|coefficients| coefficients.magnitude(freq, self.sample_rate))
+                        .product();
+                    let value = Complex::new(magnitude, 0.0);
+                    self.spectrum[bin] = value;
+                    // Mirrored, so the impulse response comes out real
+                    self.spectrum[(DESIGN_SIZE - bin) % DESIGN_SIZE] = value;
+                }
+
+                if end > DESIGN_SIZE / 2 {
+                    DesignStep::Transform(0)
+                } else {
+                    DesignStep::Sample(end)
+                }
+            }
+            DesignStep::Transform(step) => {
+                self.fft.inverse_step(&mut self.spectrum, step);
+                if step + 1 == self.fft.inverse_steps() {
+                    DesignStep::Window
+                } else {
+                    DesignStep::Transform(step + 1)
+                }
+            }
+            DesignStep::Window => {
+                // The zero phase response is centred around the first sample and wraps around
+                let centre = KERNEL_LEN / 2;
+                for (n, (tap, window)) in self.kernel.iter_mut().zip(&self.window).enumerate() {
+                    let index = (n + DESIGN_SIZE - centre) % DESIGN_SIZE;
+                    *tap = self.spectrum[index].re * window;
+                }
+                DesignStep::Partition(0)
+            }
+            DesignStep::Partition(partition) => {
+                self.convolver.prepare_partition(&self.kernel, partition);
+                if partition + 1 == self.convolver.partitions() {
+                    self.convolver.commit_kernel();
+                    DesignStep::Idle
+                } else {
+                    DesignStep::Partition(partition + 1)
+                }
+            }
+        };
+    }
+}
@@ -0,0 +1,2 @@
+pub mod band;
+pub mod linear_phase;
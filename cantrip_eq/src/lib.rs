@@ -0,0 +1,435 @@
+use nih_plug::prelude::*;
+use std::sync::Arc;
+
+mod constants;
+mod dsp;
+mod parameters;
+
+use constants::*;
+use dsp::band::Band;
+use dsp::linear_phase::LinearPhase;
+use parameters::{CantripEqParams, PhaseMode, NUM_BANDS};
+
+struct CantripEq {
+    params: Arc<CantripEqParams>,
+    bands: [Band; NUM_BANDS],
+    /// The bands' combined curve as an FIR filter, used in linear phase mode
+    linear_phase: LinearPhase,
+    /// Whether the bands have changed since the linear phase filter was designed
+    response_changed: bool,
+    /// Phase mode the latency was last reported for
+    phase_mode: PhaseMode,
+    /// Whether the mono input needs to be copied to the second output channel
+    mono_to_stereo: bool,
+    sample_rate: f32,
+}
+
+impl Default for CantripEq {
+    fn default() -> Self {
+        Self {
+            params: Arc::new(CantripEqParams::default()),
+            bands: [Band::new(); NUM_BANDS],
+            linear_phase: LinearPhase::new(NUM_BANDS),
+            response_changed: true,
+            phase_mode: PhaseMode::Minimum,
+            mono_to_stereo: false,
+            sample_rate: 44100.0,
+        }
+    }
+}
+
+impl Plugin for CantripEq {
+    const NAME: &'static str = NAME;
+    const VENDOR: &'static str = VENDOR;
+    const URL: &'static str = URL;
+    const EMAIL: &'static str = EMAIL;
+    const VERSION: &'static str = VERSION;
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+    ];
+
+    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.mono_to_stereo = audio_io_layout.main_input_channels == NonZeroU32::new(1)
+            && audio_io_layout.main_output_channels == NonZeroU32::new(2);
+        self.sample_rate = buffer_config.sample_rate;
+        self.phase_mode = self.params.phase.value();
+        self.reset();
+        context.set_latency_samples(self.latency());
+        true
+    }
+
+    fn reset(&mut self) {
+        for band in &mut self.bands {
+            band.reset();
+        }
+        self.linear_phase.reset();
+        self.response_changed = true;
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        let design = self.params.design.value();
+
+        // Switching modes changes the latency, and both paths start over from silence
+        let phase_mode = self.params.phase.value();
+        if phase_mode != self.phase_mode {
+            self.phase_mode = phase_mode;
+            self.reset();
+            context.set_latency_samples(self.latency());
+        }
+
+        for mut channel_samples in buffer.iter_samples() {
+            if self.mono_to_stereo {
+                // Only the first channel holds input, the second one is ours to fill
+                let mono = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+                if let Some(right) = channel_samples.get_mut(1) {
+                    *right = mono;
+                }
+            }
+
+            for (band, band_params) in self.bands.iter_mut().zip(&self.params.bands) {
+                self.response_changed |= band.update(
+                    band_params.enabled.value(),
+                    band_params.filter_type.value(),
+                    design,
+                    band_params.frequency.smoothed.next(),
+                    band_params.resonance.smoothed.next(),
+                    band_params.gain.smoothed.next(),
+                    self.sample_rate,
+                );
+            }
+
+            let output_gain = self.params.output_gain.smoothed.next();
+
+            match phase_mode {
+                PhaseMode::Minimum => {
+                    for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+                        let filtered = self
+                            .bands
+                            .iter_mut()
+                            .fold(*sample, |input, band| band.process(channel_idx, input));
+                        *sample = filtered * output_gain;
+                    }
+                }
+                PhaseMode::Linear => {
+                    if self.response_changed && self.linear_phase.is_ready() {
+                        self.design_linear_phase();
+                    }
+
+                    let left = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+                    let right = channel_samples.get_mut(1).map_or(0.0, |sample| *sample);
+                    let outputs = self.linear_phase.process(left, right);
+                    for (sample, output) in channel_samples.iter_mut().zip(outputs) {
+                        *sample = output * output_gain;
+                    }
+                }
+            }
+        }
+
+        ProcessStatus::Normal
+    }
+}
+
+impl CantripEq {
+    fn latency(&self) -> u32 {
+        match self.phase_mode {
+            PhaseMode::Minimum => 0,
+            PhaseMode::Linear => self.linear_phase.latency(),
+        }
+    }
+
+    /// Start turning the current band settings into a new linear phase filter.
+    fn design_linear_phase(&mut self) {
+        self.linear_phase.design(
+            self.bands.iter().filter_map(Band::coefficients),
+            self.sample_rate,
+        );
+        self.response_changed = false;
+    }
+}
+
+impl ClapPlugin for CantripEq {
+    const CLAP_ID: &'static str = CLAP_ID;
+    const CLAP_DESCRIPTION: Option<&'static str> = CLAP_DESCRIPTION;
+    const CLAP_MANUAL_URL: Option<&'static str> = CLAP_MANUAL_URL;
+    const CLAP_SUPPORT_URL: Option<&'static str> = CLAP_SUPPORT_URL;
+    const CLAP_FEATURES: &'static [ClapFeature] = CLAP_FEATURES;
+}
+
+impl Vst3Plugin for CantripEq {
+    const VST3_CLASS_ID: [u8; 16] = VST3_CLASS_ID;
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] = VST3_SUBCATEGORIES;
+}
+
+nih_export_clap!(CantripEq);
+nih_export_vst3!(CantripEq);
+
+#[cfg(test)]
+mod tests {
+    use super::dsp::band::Band;
+    use super::dsp::linear_phase::{LinearPhase, BLOCK_SIZE, DESIGN_INTERVAL_MS, KERNEL_LEN};
+    use grimoire_dsp::coefficients::BiquadCoefficients;
+    use grimoire_dsp::filter_type::{Design, FilterType};
+    use grimoire_dsp::test_util;
+
+    /// Gain of a chain of bands for a sine at `freq` once the filters have settled.
+    fn sine_gain(bands: &mut [Band], freq: f64) -> f32 {
+        let process = |input| {
+            bands
+                .iter_mut()
+                .fold(input, |sample, band| band.process(0, sample))
+        };
+        test_util::sine_gain(process, freq, 1.0, 44100.0)
+    }
+
+    /// Combined linear gain of the enabled bands at the given frequency.
+    fn curve_magnitude(bands: &[Band], freq: f32) -> f32 {
+        bands
+            .iter()
+            .filter_map(Band::coefficients)
+            .map(|coefficients| coefficients.magnitude(freq, 44100.0))
+            .product()
+    }
+
+    #[test]
+    fn test_flat_bands_are_transparent() {
+        let mut bands = [Band::new(); 2];
+        bands[0].update(
+            true,
+            FilterType::Peaking,
+            Design::Matched,
+            1000.0,
+            0.707,
+            0.0,
+            44100.0,
+        );
+        bands[1].update(
+            false,
+            FilterType::HighPass,
+            Design::Matched,
+            5000.0,
+            0.707,
+            0.0,
+            44100.0,
+        );
+
+        for freq in [100.0, 1000.0, 10000.0] {
+            let gain = sine_gain(&mut bands, freq);
+            assert!(
+                (gain - 1.0).abs() < 1e-3,
+                "Expected unity gain at {}Hz, got {}",
+                freq,
+                gain
+            );
+        }
+    }
+
+    #[test]
+    fn test_bands_add_up() {
+        let mut bands = [Band::new(); 2];
+        bands[0].update(
+            true,
+            FilterType::Peaking,
+            Design::Matched,
+            1000.0,
+            2.0,
+            6.0,
+            44100.0,
+        );
+        bands[1].update(
+            true,
+            FilterType::Peaking,
+            Design::Matched,
+            1000.0,
+            2.0,
+            6.0,
+            44100.0,
+        );
+
+        // Two +6dB bands at the same frequency boost it by 12dB
+        let gain = sine_gain(&mut bands, 1000.0);
+        let expected = 10.0f32.powf(12.0 / 20.0);
+        assert!(
+            (gain - expected).abs() < 0.01 * expected,
+            "Expected {}, got {}",
+            expected,
+            gain
+        );
+    }
+
+    /// Impulse response of the linear phase version of a chain of bands, starting at the reported
+    /// latency minus half the kernel, so the centre tap lands at `KERNEL_LEN / 2`.
+    fn linear_phase_impulse(bands: &[Band]) -> Vec<f32> {
+        let mut linear_phase = LinearPhase::new(bands.len());
+        linear_phase.design(bands.iter().filter_map(Band::coefficients), 44100.0);
+
+        // Let the design finish and fade the kernel in before sending the impulse
+        let start = 1024;
+        let latency = linear_phase.latency() as usize;
+        let outputs: Vec<f32> = (0..start + latency + KERNEL_LEN)
+            .map(|i| linear_phase.process((i == start) as u8 as f32, 0.0)[0])
+            .collect();
+        outputs[start + latency - KERNEL_LEN / 2..][..KERNEL_LEN].to_vec()
+    }
+
+    #[test]
+    fn test_linear_phase_latency() {
+        // A flat curve is a pure delay by exactly the reported latency
+        let impulse = linear_phase_impulse(&[Band::new(); 2]);
+        for (i, sample) in impulse.iter().enumerate() {
+            let expected = if i == KERNEL_LEN / 2 { 1.0 } else { 0.0 };
+            assert!(
+                (sample - expected).abs() < 1e-4,
+                "Sample {}: {} != {}",
+                i,
+                sample,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_linear_phase_matches_bands() {
+        let mut bands = [Band::new(); 2];
+        bands[0].update(
+            true,
+            FilterType::Peaking,
+            Design::Matched,
+            1000.0,
+            1.0,
+            6.0,
+            44100.0,
+        );
+        bands[1].update(
+            true,
+            FilterType::HighShelf,
+            Design::Matched,
+            5000.0,
+            0.707,
+            -4.0,
+            44100.0,
+        );
+        let impulse = linear_phase_impulse(&bands);
+
+        // A symmetric impulse response delays every frequency by the same amount
+        for i in 0..KERNEL_LEN / 2 {
+            let (early, late) = (impulse[i], impulse[KERNEL_LEN - 1 - i]);
+            assert!(
+                (early - late).abs() < 1e-4,
+                "Tap {}: {} != {}",
+                i,
+                early,
+                late
+            );
+        }
+
+        // Same magnitude response as the IIR bands
+        for freq in [200.0, 1000.0, 3000.0, 8000.0, 15000.0] {
+            let w = std::f64::consts::TAU * freq / 44100.0;
+            let (re, im) = impulse
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (n, &tap)| {
+                    let phase = w * n as f64;
+                    (re + tap as f64 * phase.cos(), im - tap as f64 * phase.sin())
+                });
+            let actual = 20.0 * (re.hypot(im)).log10();
+            let expected = 20.0 * curve_magnitude(&bands, freq as f32).log10() as f64;
+            assert!(
+                (actual - expected).abs() < 0.1,
+                "Expected {}dB at {}Hz, got {}dB",
+                expected,
+                freq,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_linear_phase_limits_redesigns() {
+        let mut linear_phase = LinearPhase::new(0);
+        assert!(linear_phase.is_ready());
+        linear_phase.design([], 44100.0);
+
+        // A smoothing band changes the curve on every sample, but it's only redesigned so often
+        let ready_after = (0..44100)
+            .position(|_| {
+                linear_phase.process(0.0, 0.0);
+                linear_phase.is_ready()
+            })
+            .unwrap();
+        let interval = (DESIGN_INTERVAL_MS * 0.001 * 44100.0) as usize;
+        assert_eq!(ready_after + 1, interval);
+    }
+    #[test]
+    fn test_linear_phase_redesigns_at_block_boundary() {
+        let mut linear_phase = LinearPhase::new(1);
+        linear_phase.design([], 44100.0);
+        let settled = (0..4 * KERNEL_LEN)
+            .map(|_| linear_phase.process(1.0, 1.0)[0])
+            .last()
+            .unwrap();
+
+        // Start halving the gain partway into a block
+        let start = 4 * KERNEL_LEN + 100;
+        for _ in 4 * KERNEL_LEN..start {
+            let output = linear_phase.process(1.0, 1.0)[0];
+            assert!((output - settled).abs() < 1e-4);
+        }
+        let half = BiquadCoefficients {
+            b0: 0.5,
+            ..BiquadCoefficients::default()
+        };
+        linear_phase.design([half], 44100.0);
+
+        // The design is spread over the following samples, the output only changes once the
+        // new kernel is swapped in at the start of a block
+        let changed_at = (start..start + 4 * BLOCK_SIZE)
+            .find(|_| (linear_phase.process(1.0, 1.0)[0] - settled).abs() > 1e-4)
+            .unwrap();
+        assert_eq!(changed_at % BLOCK_SIZE, 0);
+        assert!(changed_at < start + 2 * BLOCK_SIZE);
+    }
+}
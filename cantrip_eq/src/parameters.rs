@@ -0,0 +1,124 @@
+use nih_plug::prelude::*;
+
+use grimoire_dsp::filter_type::{Design, FilterType};
+
+/// Number of EQ bands.
+pub const NUM_BANDS: usize = 8;
+
+/// Default band frequencies, spread across the audible range.
+const DEFAULT_FREQUENCIES: [f32; NUM_BANDS] =
+    [60.0, 150.0, 400.0, 1000.0, 2500.0, 5000.0, 10000.0, 15000.0];
+
+#[derive(Params)]
+pub struct CantripEqParams {
+    /// The bands' parameter IDs get the band number as a suffix (`freq_1`, `freq_2`, ...), so
+    /// the array must only ever grow at the end
+    #[nested(array, group = "Band")]
+    pub bands: [BandParams; NUM_BANDS],
+
+    /// How the band coefficients are derived, shared by every band
+    #[id = "design"]
+    pub design: EnumParam<Design>,
+
+    /// Minimum phase runs the bands as IIR filters without latency, linear phase turns their
+    /// combined curve into one FIR filter that shifts every frequency by the same delay
+    #[id = "phase"]
+    pub phase: EnumParam<PhaseMode>,
+
+    /// Output gain
+    #[id = "output"]
+    pub output_gain: FloatParam,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum PhaseMode {
+    #[name = "Minimum Phase"]
+    Minimum,
+    #[name = "Linear Phase"]
+    Linear,
+}
+
+#[derive(Params)]
+pub struct BandParams {
+    #[id = "enabled"]
+    pub enabled: BoolParam,
+
+    #[id = "type"]
+    pub filter_type: EnumParam<FilterType>,
+
+    #[id = "freq"]
+    pub frequency: FloatParam,
+
+    #[id = "q"]
+    pub resonance: FloatParam,
+
+    /// Gain for Peaking EQ, Low Shelf, High Shelf and the other boosting types (in dB)
+    #[id = "gain"]
+    pub gain: FloatParam,
+}
+
+impl BandParams {
+    fn new(frequency: f32) -> Self {
+        Self {
+            enabled: BoolParam::new("Enabled", true),
+            filter_type: EnumParam::new("Type", FilterType::Peaking),
+            frequency: FloatParam::new(
+                "Frequency",
+                frequency,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            resonance: FloatParam::new(
+                "Q",
+                0.707,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(0.5),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            gain: FloatParam::new(
+                "Gain",
+                0.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+        }
+    }
+}
+
+impl Default for CantripEqParams {
+    fn default() -> Self {
+        Self {
+            bands: DEFAULT_FREQUENCIES.map(BandParams::new),
+            design: EnumParam::new("Design", Design::Matched),
+            phase: EnumParam::new("Phase", PhaseMode::Minimum),
+            output_gain: FloatParam::new(
+                "Output Gain",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-30.0),
+                    max: util::db_to_gain(30.0),
+                    factor: FloatRange::gain_skew_factor(-30.0, 30.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+        }
+    }
+}
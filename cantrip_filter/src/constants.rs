@@ -10,7 +10,12 @@ pub const CLAP_ID: &str = "com.flathill404.grimoire.cantrip_filter";
 pub const CLAP_DESCRIPTION: Option<&str> = Some("Simple Biquad Filter");
 pub const CLAP_MANUAL_URL: Option<&str> = Some(URL);
 pub const CLAP_SUPPORT_URL: Option<&str> = None;
-pub const CLAP_FEATURES: &[ClapFeature] = &[ClapFeature::AudioEffect, ClapFeature::Filter, ClapFeature::Stereo];
+pub const CLAP_FEATURES: &[ClapFeature] = &[
+    ClapFeature::AudioEffect,
+    ClapFeature::Filter,
+    ClapFeature::Stereo,
+    ClapFeature::Mono,
+];
 
 // Use reference to byte array for VST3 ID to match signature if needed, or just public const
 pub const VST3_CLASS_ID: [u8; 16] = *b"hCfVdKlz609eczKi";
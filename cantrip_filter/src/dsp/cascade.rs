@@ -0,0 +1,360 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+use grimoire_dsp::biquad::Biquad;
+use grimoire_dsp::coefficients::BiquadCoefficients;
+use grimoire_dsp::filter_type::{Design, FilterType};
+use grimoire_dsp::prototype::{AnalogPrototype, Prototype};
+
+use crate::parameters::Slope;
+
+/// Number of sections needed for the steepest slope.
+pub const MAX_SECTIONS: usize = 4;
+
+/// A chain of biquads for slopes steeper than 12dB/oct.
+///
+/// Low and high passes get one second-order section per 12dB/oct, with the Q of every section
+/// chosen so the chain as a whole has a Butterworth or Linkwitz-Riley response. The other filter
+/// types always use a single section.
+///
+/// With a prototype other than Butterworth, the plain low and high passes follow that classic
+/// design instead, and ignore the resonance and the design method.
+#[derive(Clone, Debug)]
+pub struct BiquadCascade {
+    sections: [Biquad; MAX_SECTIONS],
+    active: usize,
+    prototype: Prototype,
+    ripple_db: f32,
+    attenuation_db: f32,
+    /// Analog prototype for the current settings and slope, redesigned without allocating when
+    /// any of them change
+    analog: Option<AnalogPrototype>,
+}
+
+impl BiquadCascade {
+    pub fn new() -> Self {
+        Self {
+            sections: [Biquad::new(); MAX_SECTIONS],
+            active: 1,
+            prototype: Prototype::Butterworth,
+            ripple_db: 1.0,
+            attenuation_db: 60.0,
+            analog: None,
+        }
+    }
+
+    /// Pick the classic design of the low and high passes. Takes effect on the next update.
+    pub fn set_prototype(&mut self, prototype: Prototype, ripple_db: f32, attenuation_db: f32) {
+        if (prototype, ripple_db, attenuation_db)
+            != (self.prototype, self.ripple_db, self.attenuation_db)
+        {
+            self.prototype = prototype;
+            self.ripple_db = ripple_db;
+            self.attenuation_db = attenuation_db;
+            self.analog = None;
+        }
+    }
+
+    /// Reset the state of every section to zero.
+    pub fn reset(&mut self) {
+        for section in &mut self.sections {
+            section.reset();
+        }
+    }
+
+    /// Update the coefficients of every section for the given filter type, slope, design method
+    /// and parameters.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(
+        &mut self,
+        filter_type: FilterType,
+        slope: Slope,
+        design: Design,
+        freq: f32,
+        q: f32,
+        gain_db: f32,
+        sample_rate: f32,
+    ) {
+        let classic = matches!(filter_type, FilterType::LowPass | FilterType::HighPass)
+            && self.prototype != Prototype::Butterworth;
+        if classic {
+            self.update_classic(filter_type, slope, freq, sample_rate);
+            return;
+        }
+
+        let (section_type, qs, count) = match filter_type {
+            FilterType::LowPass => (FilterType::LowPass, resonant_qs(slope, q), slope.sections()),
+            FilterType::HighPass => (
+                FilterType::HighPass,
+                resonant_qs(slope, q),
+                slope.sections(),
+            ),
+            FilterType::ButterworthLP => (
+                FilterType::LowPass,
+                butterworth_qs(slope.sections()),
+                slope.sections(),
+            ),
+            FilterType::ButterworthHP => (
+                FilterType::HighPass,
+                butterworth_qs(slope.sections()),
+                slope.sections(),
+            ),
+            FilterType::LinkwitzRileyLP => (
+                FilterType::LowPass,
+                linkwitz_riley_qs(slope.sections()),
+                slope.sections(),
+            ),
+            FilterType::LinkwitzRileyHP => (
+                FilterType::HighPass,
+                linkwitz_riley_qs(slope.sections()),
+                slope.sections(),
+            ),
+            _ => (filter_type, [q; MAX_SECTIONS], 1),
+        };
+
+        self.activate(count);
+        for (section, q) in self.sections[..count].iter_mut().zip(qs) {
+            let coeffs =
+                section_type.compute_coefficients_with(design, freq, q, gain_db, sample_rate);
+            section.set_coefficients(coeffs);
+        }
+    }
+
+    fn update_classic(
+        &mut self,
+        filter_type: FilterType,
+        slope: Slope,
+        freq: f32,
+        sample_rate: f32,
+    ) {
+        let count = slope.sections();
+        if self.analog.as_ref().map(AnalogPrototype::sections) != Some(count) {
+            self.analog = None;
+        }
+        let analog = self.analog.get_or_insert_with(|| {
+            AnalogPrototype::new(
+                self.prototype,
+                2 * count,
+                self.ripple_db,
+                self.attenuation_db,
+            )
+        });
+
+        let mut coeffs = [BiquadCoefficients::unity(); MAX_SECTIONS];
+        if filter_type == FilterType::HighPass {
+            analog.high_pass(freq, sample_rate, &mut coeffs);
+        } else {
+            analog.low_pass(freq, sample_rate, &mut coeffs);
+        }
+
+        self.activate(count);
+        for (section, coeffs) in self.sections[..count].iter_mut().zip(coeffs) {
+            section.set_coefficients(coeffs);
+        }
+    }
+
+    /// Switch to `count` sections. Sections that were idle would otherwise start from stale
+    /// state.
+    fn activate(&mut self, count: usize) {
+        for section in &mut self.sections[self.active.min(count)..count] {
+            section.reset();
+        }
+        self.active = count;
+    }
+
+    /// Process a single sample through all active sections.
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.sections[..self.active]
+            .iter_mut()
+            .fold(input, |sample, section| section.process(sample))
+    }
+}
+
+/// Q of the `k`th (1-based) second-order section of a Butterworth filter of the given order.
+fn butterworth_q(order: usize, k: usize) -> f32 {
+    let angle = (2 * k - 1) as f32 * PI / (2 * order) as f32;
+    1.0 / (2.0 * angle.sin())
+}
+
+/// Section Qs of a Butterworth filter with `sections` second-order sections.
+fn butterworth_qs(sections: usize) -> [f32; MAX_SECTIONS] {
+    let mut qs = [FRAC_1_SQRT_2; MAX_SECTIONS];
+    for (k, q) in qs.iter_mut().take(sections).enumerate() {
+        *q = butterworth_q(2 * sections, k + 1);
+    }
+    qs
+}
+
+/// Section Qs of a Butterworth response whose sharpest section follows the resonance parameter.
+/// At the default Q of 0.707 this is a plain Butterworth filter.
+fn resonant_qs(slope: Slope, q: f32) -> [f32; MAX_SECTIONS] {
+    let mut qs = butterworth_qs(slope.sections());
+    // The first section has the highest Q
+    qs[0] *= q / FRAC_1_SQRT_2;
+    qs
+}
+
+/// Section Qs of a Linkwitz-Riley filter, a Butterworth filter of half the order applied twice.
+fn linkwitz_riley_qs(sections: usize) -> [f32; MAX_SECTIONS] {
+    let mut qs = [FRAC_1_SQRT_2; MAX_SECTIONS];
+    let mut count = 0;
+
+    // An odd order Butterworth has a first-order section, squared it becomes a biquad with Q 0.5
+    if sections % 2 == 1 {
+        qs[count] = 0.5;
+        count += 1;
+    }
+    for k in 1..=sections / 2 {
+        let q = butterworth_q(sections, k);
+        qs[count] = q;
+        qs[count + 1] = q;
+        count += 2;
+    }
+
+    qs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grimoire_dsp::response::{cascade_magnitude, cascade_phase};
+    use grimoire_dsp::test_util::sine_gain;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    fn coefficients(filter: &BiquadCascade) -> Vec<BiquadCoefficients> {
+        filter.sections[..filter.active]
+            .iter()
+            .map(Biquad::coefficients)
+            .collect()
+    }
+
+    fn magnitude(filter: &BiquadCascade, freq: f32) -> f32 {
+        cascade_magnitude(&coefficients(filter), freq, SAMPLE_RATE)
+    }
+
+    fn cascade(filter_type: FilterType, slope: Slope) -> BiquadCascade {
+        let mut filter = BiquadCascade::new();
+        filter.update(
+            filter_type,
+            slope,
+            Design::Bilinear,
+            1000.0,
+            0.707,
+            0.0,
+            SAMPLE_RATE,
+        );
+        filter
+    }
+
+    #[test]
+    fn test_cascade_butterworth_slopes() {
+        for slope in [Slope::Db12, Slope::Db24, Slope::Db36, Slope::Db48] {
+            let filter = cascade(FilterType::ButterworthLP, slope);
+            assert_eq!(filter.active, slope.sections());
+
+            // Every Butterworth filter is 3dB down at the cutoff
+            let cutoff_gain = magnitude(&filter, 1000.0);
+            assert!(
+                (cutoff_gain - FRAC_1_SQRT_2).abs() < 0.01,
+                "{:?}: expected -3dB at the cutoff, got {}",
+                slope,
+                cutoff_gain
+            );
+
+            // And matches the Butterworth response above it, with the bilinear frequency warping
+            let order = 2 * slope.sections() as i32;
+            let warp = |freq: f32| (PI * freq / SAMPLE_RATE).tan();
+            let expected = 1.0 / (1.0 + (warp(4000.0) / warp(1000.0)).powi(2 * order)).sqrt();
+            let stop_gain = magnitude(&filter, 4000.0);
+            assert!(
+                (stop_gain / expected - 1.0).abs() < 0.05,
+                "{:?}: expected {} two octaves up, got {}",
+                slope,
+                expected,
+                stop_gain
+            );
+        }
+    }
+
+    #[test]
+    fn test_cascade_linkwitz_riley_sums_flat() {
+        for slope in [Slope::Db24, Slope::Db48] {
+            let low = coefficients(&cascade(FilterType::LinkwitzRileyLP, slope));
+            let high = coefficients(&cascade(FilterType::LinkwitzRileyHP, slope));
+
+            // LR4 and LR8 bands are in phase, so they add up to unity gain at every frequency
+            for freq in [200.0, 1000.0, 5000.0] {
+                let low_gain = cascade_magnitude(&low, freq, SAMPLE_RATE);
+                let high_gain = cascade_magnitude(&high, freq, SAMPLE_RATE);
+                let phase_difference = cascade_phase(&low, freq, SAMPLE_RATE)
+                    - cascade_phase(&high, freq, SAMPLE_RATE);
+                let sum = (low_gain.powi(2)
+                    + high_gain.powi(2)
+                    + 2.0 * low_gain * high_gain * phase_difference.cos())
+                .sqrt();
+                assert!(
+                    (sum - 1.0).abs() < 0.01,
+                    "{:?} at {}Hz: expected unity gain, got {}",
+                    slope,
+                    freq,
+                    sum
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cascade_classic_prototypes() {
+        let mut filter = BiquadCascade::new();
+        filter.set_prototype(Prototype::Elliptic, 1.0, 60.0);
+        filter.update(
+            FilterType::LowPass,
+            Slope::Db48,
+            Design::Bilinear,
+            1000.0,
+            0.707,
+            0.0,
+            SAMPLE_RATE,
+        );
+
+        // The passband ends at the bottom of the ripple, and an octave up the stopband has begun
+        let edge_gain = magnitude(&filter, 1000.0);
+        let expected = 10.0f32.powf(-1.0 / 20.0);
+        assert!(
+            (edge_gain - expected).abs() < 0.01,
+            "Expected {} at the passband edge, got {}",
+            expected,
+            edge_gain
+        );
+        let stop_gain = magnitude(&filter, 2000.0);
+        assert!(stop_gain < 0.0011, "Expected -60dB, got {}", stop_gain);
+
+        // Switching back to Butterworth restores the resonant response
+        filter.set_prototype(Prototype::Butterworth, 1.0, 60.0);
+        filter.update(
+            FilterType::LowPass,
+            Slope::Db48,
+            Design::Bilinear,
+            1000.0,
+            0.707,
+            0.0,
+            SAMPLE_RATE,
+        );
+        let cutoff_gain = magnitude(&filter, 1000.0);
+        assert!((cutoff_gain - FRAC_1_SQRT_2).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_cascade_processes_active_sections() {
+        // The cascade runs exactly the sections its response is computed from
+        let mut filter = cascade(FilterType::ButterworthLP, Slope::Db36);
+        let gain = sine_gain(|sample| filter.process(sample), 2000.0, 1.0, SAMPLE_RATE);
+        let expected = magnitude(&filter, 2000.0);
+        assert!(
+            (gain / expected - 1.0).abs() < 0.01,
+            "Expected {}, got {}",
+            expected,
+            gain
+        );
+    }
+}
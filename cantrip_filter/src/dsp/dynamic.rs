@@ -0,0 +1,123 @@
+use grimoire_dsp::biquad::Biquad;
+use grimoire_dsp::dynamics::{gain_reduction_db, level_db};
+use grimoire_dsp::envelope::EnvelopeFollower;
+use grimoire_dsp::filter_type::FilterType;
+
+/// Gain computer of a dynamic EQ band.
+///
+/// The detector listens to the band through a band pass at the band's frequency and Q. Once that
+/// level crosses the threshold, the band's gain moves from 0dB toward its target by as much as a
+/// compressor with the same threshold and ratio would reduce the level.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DynamicBand {
+    /// Band pass detectors for up to two channels
+    detectors: [Biquad; 2],
+    envelope: EnvelopeFollower,
+    /// Frequency and Q the detectors were tuned to, used to skip redundant updates
+    band: Option<(f32, f32)>,
+}
+
+impl DynamicBand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the detector state.
+    pub fn reset(&mut self) {
+        for detector in &mut self.detectors {
+            detector.reset();
+        }
+        self.envelope.reset();
+    }
+
+    /// Update the envelope follower timing.
+    pub fn set_times(&mut self, attack_ms: f32, release_ms: f32, sample_rate: f32) {
+        self.envelope.set_times(attack_ms, release_ms, sample_rate);
+    }
+
+    /// Tune the detectors to the band.
+    pub fn set_band(&mut self, freq: f32, q: f32, sample_rate: f32) {
+        if self.band == Some((freq, q)) {
+            return;
+        }
+        self.band = Some((freq, q));
+
+        // Band Pass peaks at 0dB at any Q, so the threshold applies to the band's actual level
+        for detector in &mut self.detectors {
+            detector.update(FilterType::BandPass, freq, q, 0.0, sample_rate);
+        }
+    }
+
+    /// Process a stereo pair of detector input and return the band's gain in dB.
+    ///
+    /// Uses the maximum of both channels for detection (linked stereo).
+    pub fn process_stereo(
+        &mut self,
+        left: f32,
+        right: f32,
+        target_db: f32,
+        threshold_db: f32,
+        ratio: f32,
+    ) -> f32 {
+        let input = self.detectors[0]
+            .process(left)
+            .abs()
+            .max(self.detectors[1].process(right).abs());
+        let envelope = self.envelope.process(input);
+
+        // The band never moves past its target
+        let reduction_db = -gain_reduction_db(level_db(envelope), threshold_db, ratio, 0.0);
+        reduction_db.min(target_db.abs()) * target_db.signum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gain of a dynamic band at 1kHz after a second of a sine at the given level. The
+    /// threshold is -20dB.
+    fn dynamic_gain(freq: f64, q: f32, amplitude: f32, target_db: f32, ratio: f32) -> f32 {
+        let mut band = DynamicBand::new();
+        band.set_band(1000.0, q, 44100.0);
+        band.set_times(1.0, 50.0, 44100.0);
+
+        let mut gain_db = 0.0;
+        for i in 0..44100 {
+            let input =
+                amplitude * (i as f64 * std::f64::consts::TAU * freq / 44100.0).sin() as f32;
+            gain_db = band.process_stereo(input, input, target_db, -20.0, ratio);
+        }
+        gain_db
+    }
+
+    #[test]
+    fn test_dynamic_band_follows_band_level() {
+        // 20dB over the threshold at 4:1 is enough to reach the target either way
+        assert!((dynamic_gain(1000.0, 1.0, 1.0, -6.0, 4.0) + 6.0).abs() < 1e-3);
+        assert!((dynamic_gain(1000.0, 1.0, 1.0, 6.0, 4.0) - 6.0).abs() < 1e-3);
+
+        // At a gentle ratio the gain only moves part of the way
+        let partial = dynamic_gain(1000.0, 1.0, 1.0, -12.0, 1.25);
+        assert!((partial + 4.0).abs() < 0.5, "Partial gain {}", partial);
+
+        // Quiet signals and loud signals outside of the band leave the gain alone
+        assert_eq!(dynamic_gain(1000.0, 1.0, 0.01, -6.0, 4.0), 0.0);
+        assert_eq!(dynamic_gain(50.0, 1.0, 1.0, -6.0, 4.0), 0.0);
+    }
+
+    #[test]
+    fn test_dynamic_band_threshold_ignores_q() {
+        // 6dB over the threshold at 4:1, a bit less than 4.5dB as the envelope ripples
+        let reference = dynamic_gain(1000.0, 1.0, 0.2, -12.0, 4.0);
+        assert!(reference < -4.0, "Reference gain {}", reference);
+
+        for q in [0.3, 10.0] {
+            // Just under the threshold the band stays put, however narrow or wide it is
+            assert_eq!(dynamic_gain(1000.0, q, 0.08, -12.0, 4.0), 0.0, "Q {}", q);
+
+            let gain = dynamic_gain(1000.0, q, 0.2, -12.0, 4.0);
+            assert!((gain - reference).abs() < 0.1, "Q {}: gain {}", q, gain);
+        }
+    }
+}
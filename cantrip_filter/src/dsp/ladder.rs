@@ -0,0 +1,169 @@
+use std::f32::consts::PI;
+
+/// Feedback at the top of the resonance range. The ladder starts to self-oscillate at 4.
+const MAX_FEEDBACK: f32 = 4.4;
+
+/// Transistor ladder low pass (24dB/oct) in the style of the Moog filter.
+///
+/// Four one-pole stages in series with negative feedback from the last stage to the input. Every
+/// stage saturates like the transistor pairs of the analog circuit. The feedback loop is solved
+/// without a unit delay by replacing each `tanh()` with its slope through the origin at the
+/// current state, which keeps the filter stable up to self-oscillation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ladder {
+    // Coefficients
+    g: f32,
+    feedback: f32,
+    drive: f32,
+    // State (integrator memories and the previous input)
+    s: [f32; 4],
+    last_input: f32,
+}
+
+impl Ladder {
+    pub fn new() -> Self {
+        Self {
+            drive: 1.0,
+            ..Self::default()
+        }
+    }
+
+    /// Reset the filter state to zero.
+    pub fn reset(&mut self) {
+        self.s = [0.0; 4];
+        self.last_input = 0.0;
+    }
+
+    /// Update the cutoff frequency, resonance and drive.
+    ///
+    /// The resonance uses the same Q range as the other engines, the filter self-oscillates
+    /// from a Q of about 5.5.
+    pub fn update(&mut self, freq: f32, q: f32, drive_db: f32, sample_rate: f32) {
+        // Clamp frequency to valid range
+        let freq = freq.clamp(1.0, sample_rate * 0.499);
+
+        self.g = (PI * freq / sample_rate).tan();
+        self.feedback = (MAX_FEEDBACK * (1.0 - 0.5 / q)).max(0.0);
+        self.drive = 10.0f32.powf(drive_db / 20.0);
+    }
+
+    /// Process a single sample.
+    pub fn process(&mut self, input: f32) -> f32 {
+        let g = self.g;
+        let k = self.feedback;
+        let s = &mut self.s;
+
+        // Drive pushes the signal further into the saturation, quiet signals keep their level
+        let input = input * self.drive;
+        let half_input = 0.5 * (input + self.last_input);
+        self.last_input = input;
+
+        // Slopes of the stage nonlinearities at the current state
+        let t0 = tanh_slope(half_input - k * s[3]);
+        let t1 = tanh_slope(s[0]);
+        let t2 = tanh_slope(s[1]);
+        let t3 = tanh_slope(s[2]);
+        let t4 = tanh_slope(s[3]);
+
+        // Denominators of the individual stage solutions
+        let g0 = 1.0 / (1.0 + g * t1);
+        let g1 = 1.0 / (1.0 + g * t2);
+        let g2 = 1.0 / (1.0 + g * t3);
+        let g3 = 1.0 / (1.0 + g * t4);
+
+        // Gains from each stage's input to the output
+        let f3 = g * t3 * g3;
+        let f2 = g * t2 * g2 * f3;
+        let f1 = g * t1 * g1 * f2;
+        let f0 = g * t0 * g0 * f1;
+
+        // Solve the feedback loop for the output, then run the stages
+        let y3 = (g3 * s[3] + f3 * g2 * s[2] + f2 * g1 * s[1] + f1 * g0 * s[0] + f0 * input)
+            / (1.0 + k * f0);
+        let x = t0 * (input - k * y3);
+        let y0 = t1 * g0 * (s[0] + g * x);
+        let y1 = t2 * g1 * (s[1] + g * y0);
+        let y2 = t3 * g2 * (s[2] + g * y1);
+
+        s[0] += 2.0 * g * (x - y0);
+        s[1] += 2.0 * g * (y0 - y1);
+        s[2] += 2.0 * g * (y1 - y2);
+        s[3] += 2.0 * g * (y2 - t4 * y3);
+
+        // Anti-denormal
+        for state in s.iter_mut() {
+            if state.abs() < 1e-15 {
+                *state = 0.0;
+            }
+        }
+
+        y3 / self.drive
+    }
+}
+
+/// `tanh(x) / x`, approximated with a Padé approximant.
+fn tanh_slope(x: f32) -> f32 {
+    let x2 = x * x;
+    ((x2 + 105.0) * x2 + 945.0) / ((15.0 * x2 + 420.0) * x2 + 945.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grimoire_dsp::test_util::sine_gain;
+
+    /// Gain for a sine at `freq` with the given amplitude, starting from a silent ladder.
+    fn ladder_gain(ladder: &mut Ladder, freq: f64, amplitude: f32) -> f32 {
+        ladder.reset();
+        sine_gain(|sample| ladder.process(sample), freq, amplitude, 44100.0)
+    }
+
+    #[test]
+    fn test_ladder_low_pass_response() {
+        let mut ladder = Ladder::new();
+        ladder.update(1000.0, 0.5, 0.0, 44100.0);
+
+        // Without resonance, quiet signals see four one-pole low passes
+        let pass_gain = ladder_gain(&mut ladder, 100.0, 0.01);
+        assert!(
+            (pass_gain - 1.0).abs() < 0.02,
+            "Passband gain {}",
+            pass_gain
+        );
+        let stop_gain = ladder_gain(&mut ladder, 4000.0, 0.01);
+        assert!(stop_gain < 1.0 / 250.0, "Gain two octaves up {}", stop_gain);
+    }
+
+    #[test]
+    fn test_ladder_self_oscillates() {
+        let mut ladder = Ladder::new();
+        ladder.update(1000.0, 10.0, 0.0, 44100.0);
+
+        // A single click keeps ringing at a level set by the saturation
+        let mut peaks = [0.0f32; 4];
+        for i in 0..88200 {
+            let output = ladder.process(if i == 0 { 1.0 } else { 0.0 });
+            peaks[i / 22050] = peaks[i / 22050].max(output.abs());
+        }
+        assert!(peaks[2] > 0.05, "Oscillation died out, peak {}", peaks[2]);
+        assert!(
+            (peaks[3] / peaks[2] - 1.0).abs() < 0.01,
+            "Oscillation is not steady: {:?}",
+            peaks
+        );
+    }
+
+    #[test]
+    fn test_ladder_drive_only_adds_saturation() {
+        let mut clean = Ladder::new();
+        clean.update(1000.0, 0.707, 0.0, 44100.0);
+        let mut driven = Ladder::new();
+        driven.update(1000.0, 0.707, 24.0, 44100.0);
+
+        // Quiet signals keep their level, loud ones get squashed
+        let quiet = ladder_gain(&mut driven, 200.0, 0.001) / ladder_gain(&mut clean, 200.0, 0.001);
+        assert!((quiet - 1.0).abs() < 0.01, "Quiet gain ratio {}", quiet);
+        let loud = ladder_gain(&mut driven, 200.0, 1.0) / ladder_gain(&mut clean, 200.0, 1.0);
+        assert!(loud < 0.5, "Loud gain ratio {}", loud);
+    }
+}
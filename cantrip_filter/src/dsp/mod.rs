@@ -1,2 +1,3 @@
-pub mod biquad;
-pub mod coefficients;
+pub mod cascade;
+pub mod dynamic;
+pub mod ladder;
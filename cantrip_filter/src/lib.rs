@@ -6,26 +6,90 @@ mod dsp;
 mod parameters;
 
 use constants::*;
-use dsp::biquad::Biquad;
-use parameters::CantripFilterParams;
+use dsp::cascade::BiquadCascade;
+use dsp::dynamic::DynamicBand;
+use dsp::ladder::Ladder;
+use grimoire_dsp::adsr::Adsr;
+use grimoire_dsp::envelope::EnvelopeFollower;
+use grimoire_dsp::filter_type::{Design, FilterType};
+use grimoire_dsp::lfo::Lfo;
+use grimoire_dsp::oversampling::{Oversampler, OversamplingFactor, OversamplingPhase};
+use grimoire_dsp::prototype::Prototype;
+use grimoire_dsp::svf::Svf;
+use parameters::{CantripFilterParams, FilterEngine, Slope};
 
 struct CantripFilter {
     params: Arc<CantripFilterParams>,
-    // Stereo filter state
-    filters: [Biquad; 2],
+    // Filter state for up to two channels
+    filters: [BiquadCascade; 2],
+    svfs: [Svf; 2],
+    ladders: [Ladder; 2],
+    oversamplers: [Oversampler; 2],
+    /// Input level that modulates the cutoff, shared by both channels
+    envelope: EnvelopeFollower,
+    /// Level detection that moves the filter gain when Dynamic is on
+    dynamic: DynamicBand,
+    /// One LFO per channel, so the right channel can run at a phase offset
+    lfos: [Lfo; 2],
+    /// Cutoff envelope triggered by MIDI notes
+    note_env: Adsr,
+    /// Most recent MIDI note, key tracking stays on it after the note has ended
+    last_note: Option<u8>,
+    /// The note that triggered the envelope, releasing it starts the release
+    held_note: Option<u8>,
+    /// Whether the host transport was playing during the last block, to retrigger the LFOs
+    transport_playing: bool,
+    /// Settings the current coefficients of each channel were computed for, used to skip
+    /// redundant updates
+    filter_settings: [Option<FilterSettings>; 2],
+    /// Whether the mono input needs to be copied to the second output channel
+    mono_to_stereo: bool,
     sample_rate: f32,
 }
 
+/// Everything the filter coefficients depend on.
+#[derive(Clone, Copy, PartialEq)]
+struct FilterSettings {
+    engine: FilterEngine,
+    filter_type: FilterType,
+    slope: Slope,
+    design: Design,
+    prototype: Prototype,
+    ripple: f32,
+    attenuation: f32,
+    freq: f32,
+    q: f32,
+    filter_gain: f32,
+    drive: f32,
+}
+
 impl Default for CantripFilter {
     fn default() -> Self {
         Self {
             params: Arc::new(CantripFilterParams::default()),
-            filters: [Biquad::new(); 2],
+            filters: [BiquadCascade::new(), BiquadCascade::new()],
+            svfs: [Svf::new(); 2],
+            ladders: [Ladder::new(); 2],
+            oversamplers: [Oversampler::new(OversamplingFactor::Off, OversamplingPhase::Linear); 2],
+            envelope: EnvelopeFollower::default(),
+            dynamic: DynamicBand::new(),
+            lfos: [Lfo::new(); 2],
+            note_env: Adsr::default(),
+            last_note: None,
+            held_note: None,
+            transport_playing: false,
+            filter_settings: [None; 2],
+            mono_to_stereo: false,
             sample_rate: 44100.0,
         }
     }
 }
 
+const SIDECHAIN_PORT_NAMES: PortNames = PortNames {
+    aux_inputs: &["Sidechain"],
+    ..PortNames::const_default()
+};
+
 impl Plugin for CantripFilter {
     const NAME: &'static str = NAME;
     const VENDOR: &'static str = VENDOR;
@@ -33,15 +97,31 @@ impl Plugin for CantripFilter {
     const EMAIL: &'static str = EMAIL;
     const VERSION: &'static str = VERSION;
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
-        aux_input_ports: &[],
-        aux_output_ports: &[],
-        names: PortNames::const_default(),
-    }];
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[],
+            names: SIDECHAIN_PORT_NAMES,
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[],
+            names: SIDECHAIN_PORT_NAMES,
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            aux_output_ports: &[],
+            names: SIDECHAIN_PORT_NAMES,
+        },
+    ];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
 
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
@@ -55,48 +135,219 @@ impl Plugin for CantripFilter {
 
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
+        audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
+        self.mono_to_stereo = audio_io_layout.main_input_channels == NonZeroU32::new(1)
+            && audio_io_layout.main_output_channels == NonZeroU32::new(2);
         self.sample_rate = buffer_config.sample_rate;
-        for filter in &mut self.filters {
-            filter.reset();
-        }
+        self.filter_settings = [None; 2];
+        self.reset_filters();
+        self.reset_modulation();
+        self.update_oversampling();
+        context.set_latency_samples(self.oversamplers[0].latency());
         true
     }
 
     fn reset(&mut self) {
-        for filter in &mut self.filters {
-            filter.reset();
+        self.reset_filters();
+        self.reset_modulation();
+        for oversampler in &mut self.oversamplers {
+            oversampler.reset();
         }
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        // Update filter coefficients once per block
+        if let Some(latency) = self.update_oversampling() {
+            context.set_latency_samples(latency);
+        }
+        // The filters run at the oversampled rate
+        let filter_rate = self.sample_rate * self.oversamplers[0].ratio() as f32;
+
+        let engine = self.params.engine.value();
         let filter_type = self.params.filter_type.value();
-        let freq = self.params.frequency.value();
-        let q = self.params.resonance.value();
-        let filter_gain = self.params.filter_gain.value();
+        let slope = self.params.slope.value();
+        let design = self.params.design.value();
+        let prototype = self.params.prototype.value();
+        let ripple = self.params.ripple.value();
+        let attenuation = self.params.attenuation.value();
+        let channel_mode = self.params.channel_mode.value();
 
-        for filter in &mut self.filters {
-            filter.update(filter_type, freq, q, filter_gain, self.sample_rate);
-        }
+        // The envelope and the dynamic band run at the host rate, and listen to the sidechain
+        // if it's enabled and connected
+        self.envelope.set_times(
+            self.params.env_attack.value(),
+            self.params.env_release.value(),
+            self.sample_rate,
+        );
+        let env_sidechain = self.params.env_sidechain.value();
+        let dynamic = self.params.dynamic.value();
+        let dyn_threshold = self.params.dyn_threshold.value();
+        let dyn_ratio = self.params.dyn_ratio.value();
+        let dyn_sidechain = self.params.dyn_sidechain.value();
+        self.dynamic.set_times(
+            self.params.dyn_attack.value(),
+            self.params.dyn_release.value(),
+            self.sample_rate,
+        );
+        let sidechain = aux.inputs.first().map(|input| input.as_slice_immutable());
+
+        self.update_lfos(context.transport());
+        self.note_env.set_parameters(
+            self.params.note_env_attack.value(),
+            self.params.note_env_decay.value(),
+            self.params.note_env_sustain.value() / 100.0,
+            self.params.note_env_release.value(),
+            self.sample_rate,
+        );
+
+        let mut next_event = context.next_event();
 
         // Process sample by sample
         // iter_samples() iterates per-sample, giving access to all channels for each sample
-        for mut channel_samples in buffer.iter_samples() {
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            while let Some(event) = next_event {
+                if event.timing() > sample_idx as u32 {
+                    break;
+                }
+
+                match event {
+                    NoteEvent::NoteOn { note, .. } => {
+                        self.note_env.note_on();
+                        self.last_note = Some(note);
+                        self.held_note = Some(note);
+                    }
+                    NoteEvent::NoteOff { note, .. } if self.held_note == Some(note) => {
+                        self.note_env.note_off();
+                        self.held_note = None;
+                    }
+                    _ => (),
+                }
+
+                next_event = context.next_event();
+            }
+
+            if self.mono_to_stereo {
+                // Only the first channel holds input, the second one is ours to fill
+                let mono = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+                if let Some(right) = channel_samples.get_mut(1) {
+                    *right = mono;
+                }
+            }
+
+            let freq = self.params.frequency.smoothed.next();
+            let spread = self.params.spread.smoothed.next();
+            let q = self.params.resonance.smoothed.next();
+            let filter_gain = self.params.filter_gain.smoothed.next();
+            let morph = self.params.morph.smoothed.next();
+            let drive = self.params.drive.smoothed.next();
+            let env_depth = self.params.env_depth.smoothed.next();
+            let lfo_cutoff_depth = self.params.lfo_cutoff_depth.smoothed.next();
+            let lfo_resonance_depth = self.params.lfo_resonance_depth.smoothed.next();
+            let key_tracking = self.params.key_tracking.smoothed.next();
+            let note_env_depth = self.params.note_env_depth.smoothed.next();
             let gain = self.params.gain.smoothed.next();
 
+            // Channel modes only apply to stereo, mono is always filtered
+            let num_channels = channel_samples.len();
+            let stereo = num_channels == 2;
+            let mut frame = [0.0; 2];
             for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
-                if channel_idx < self.filters.len() {
-                    *sample = self.filters[channel_idx].process(*sample) * gain;
+                frame[channel_idx] = *sample;
+            }
+
+            // With a single sidechain channel both sides of the detectors see the same signal
+            let sidechain_frame = sidechain.map(|channels| {
+                let last_channel = channels.len() - 1;
+                [channels[0][sample_idx], channels[last_channel][sample_idx]]
+            });
+            let detector_input = |use_sidechain: bool| match sidechain_frame {
+                Some(sidechain_frame) if use_sidechain => sidechain_frame,
+                _ => frame,
+            };
+
+            // Linked detection on the loudest channel
+            let [left, right] = detector_input(env_sidechain);
+            let envelope = self.envelope.process(left.abs().max(right.abs()));
+            let key_octaves = self
+                .last_note
+                .map_or(0.0, |note| key_tracking_octaves(note, key_tracking / 100.0));
+            let note_env = self.note_env.process();
+            // Everything but the LFO moves both channels the same way
+            let octaves = env_depth * envelope.min(1.0) + key_octaves + note_env_depth * note_env;
+
+            // The dynamic band listens at the band's own frequency, without the modulation
+            let filter_gain = if dynamic {
+                let [left, right] = detector_input(dyn_sidechain);
+                self.dynamic.set_band(freq, q, self.sample_rate);
+                self.dynamic
+                    .process_stereo(left, right, filter_gain, dyn_threshold, dyn_ratio)
+            } else {
+                filter_gain
+            };
+
+            let mid_side = stereo && channel_mode.is_mid_side();
+            if mid_side {
+                frame = encode_mid_side(frame);
+            }
+
+            for (channel_idx, sample) in frame.iter_mut().take(num_channels).enumerate() {
+                let lfo = self.lfos[channel_idx].process();
+                let filtered = !stereo || channel_mode.filters(channel_idx);
+                let spread_octaves = if stereo {
+                    spread * (channel_idx as f32 - 0.5)
+                } else {
+                    0.0
+                };
+
+                // Coefficients follow the modulated values every sample, but only while they move
+                if filtered {
+                    self.update_filter(
+                        channel_idx,
+                        FilterSettings {
+                            engine,
+                            filter_type,
+                            slope,
+                            design,
+                            prototype,
+                            ripple,
+                            attenuation,
+                            freq: modulate_frequency(
+                                freq,
+                                octaves + spread_octaves + lfo * lfo_cutoff_depth,
+                            ),
+                            q: modulate_resonance(q, lfo * lfo_resonance_depth),
+                            filter_gain,
+                            drive,
+                        },
+                        filter_rate,
+                    );
                 }
+
+                // Unfiltered channels still pass through the oversampler to keep the latency
+                // the same on both sides
+                let filter = &mut self.filters[channel_idx];
+                let svf = &mut self.svfs[channel_idx];
+                let ladder = &mut self.ladders[channel_idx];
+                *sample = self.oversamplers[channel_idx].process(*sample, |input| match engine {
+                    _ if !filtered => input,
+                    FilterEngine::Biquad => filter.process(input),
+                    FilterEngine::StateVariable => svf.process(input).morph(morph),
+                    FilterEngine::Ladder => ladder.process(input),
+                });
+            }
+
+            if mid_side {
+                frame = decode_mid_side(frame);
+            }
+            for (sample, output) in channel_samples.iter_mut().zip(frame) {
+                *sample = output * gain;
             }
         }
 
@@ -104,6 +355,148 @@ impl Plugin for CantripFilter {
     }
 }
 
+impl CantripFilter {
+    fn reset_filters(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        for svf in &mut self.svfs {
+            svf.reset();
+        }
+        for ladder in &mut self.ladders {
+            ladder.reset();
+        }
+    }
+
+    fn reset_modulation(&mut self) {
+        self.envelope.reset();
+        self.dynamic.reset();
+        self.note_env.reset();
+        self.last_note = None;
+        self.held_note = None;
+        for lfo in &mut self.lfos {
+            lfo.reset();
+        }
+        self.transport_playing = false;
+    }
+
+    /// Recompute a channel's coefficients if anything they depend on has changed.
+    fn update_filter(&mut self, channel_idx: usize, settings: FilterSettings, filter_rate: f32) {
+        if self.filter_settings[channel_idx] == Some(settings) {
+            return;
+        }
+        self.filter_settings[channel_idx] = Some(settings);
+
+        let FilterSettings {
+            engine,
+            filter_type,
+            slope,
+            design,
+            prototype,
+            ripple,
+            attenuation,
+            freq,
+            q,
+            filter_gain,
+            drive,
+        } = settings;
+        match engine {
+            FilterEngine::Biquad => {
+                let filter = &mut self.filters[channel_idx];
+                filter.set_prototype(prototype, ripple, attenuation);
+                filter.update(
+                    filter_type,
+                    slope,
+                    design,
+                    freq,
+                    q,
+                    filter_gain,
+                    filter_rate,
+                );
+            }
+            FilterEngine::StateVariable => self.svfs[channel_idx].update(freq, q, filter_rate),
+            FilterEngine::Ladder => self.ladders[channel_idx].update(freq, q, drive, filter_rate),
+        }
+    }
+
+    /// Set the LFO rate and phases for the next block. Synced LFOs follow the host's song
+    /// position, and both modes start over when the transport starts.
+    fn update_lfos(&mut self, transport: &Transport) {
+        let division = self.params.lfo_division.value();
+        let synced = self.params.lfo_sync.value();
+        // Falls back to the free-running rate if the host doesn't report a tempo
+        let rate = transport
+            .tempo
+            .filter(|_| synced)
+            .map_or(self.params.lfo_rate.value(), |tempo| {
+                division.rate_hz(tempo as f32)
+            });
+        let offset = self.params.lfo_stereo_offset.value() / 360.0;
+
+        let restart = transport.playing && !self.transport_playing;
+        self.transport_playing = transport.playing;
+        let phase = transport
+            .pos_beats()
+            .filter(|_| synced && transport.playing)
+            .map(|pos_beats| division.phase(pos_beats));
+
+        for (channel_idx, lfo) in self.lfos.iter_mut().enumerate() {
+            lfo.set_shape(self.params.lfo_shape.value());
+            lfo.set_rate(rate, self.sample_rate);
+            lfo.set_phase_offset(if channel_idx == 1 { offset } else { 0.0 });
+            if restart {
+                lfo.reset();
+            }
+            if let Some(phase) = phase {
+                lfo.set_phase(phase);
+            }
+        }
+    }
+
+    /// Follow the oversampling parameters. Returns the new latency if the mode has changed.
+    fn update_oversampling(&mut self) -> Option<u32> {
+        let factor = self.params.oversampling.value();
+        let phase = self.params.oversampling_phase.value();
+        if factor == self.oversamplers[0].factor() && phase == self.oversamplers[0].phase() {
+            return None;
+        }
+
+        for oversampler in &mut self.oversamplers {
+            oversampler.set_mode(factor, phase);
+        }
+        // The coefficients depend on the oversampled rate, and the old state no longer fits
+        self.filter_settings = [None; 2];
+        self.reset_filters();
+
+        Some(self.oversamplers[0].latency())
+    }
+}
+
+/// Shift a cutoff frequency by a number of octaves, keeping it within the frequency range.
+fn modulate_frequency(freq: f32, octaves: f32) -> f32 {
+    (freq * octaves.exp2()).clamp(20.0, 20000.0)
+}
+
+/// Split left and right into mid and side.
+fn encode_mid_side([left, right]: [f32; 2]) -> [f32; 2] {
+    [(left + right) * 0.5, (left - right) * 0.5]
+}
+
+/// Turn mid and side back into left and right.
+fn decode_mid_side([mid, side]: [f32; 2]) -> [f32; 2] {
+    [mid + side, mid - side]
+}
+
+/// Cutoff shift for a MIDI note relative to middle C, where an amount of 1 follows the keyboard.
+fn key_tracking_octaves(note: u8, amount: f32) -> f32 {
+    (note as f32 - 60.0) / 12.0 * amount
+}
+
+/// Scale a resonance by a number of octaves, keeping it within the resonance range.
+fn modulate_resonance(q: f32, octaves: f32) -> f32 {
+    (q * octaves.exp2()).clamp(0.1, 10.0)
+}
+
 impl ClapPlugin for CantripFilter {
     const CLAP_ID: &'static str = CLAP_ID;
     const CLAP_DESCRIPTION: Option<&'static str> = CLAP_DESCRIPTION;
@@ -122,36 +515,81 @@ nih_export_vst3!(CantripFilter);
 
 #[cfg(test)]
 mod tests {
-    use super::dsp::biquad::Biquad;
-    use super::parameters::FilterType;
+    use super::parameters::ChannelMode;
+    use super::{decode_mid_side, encode_mid_side, key_tracking_octaves, modulate_frequency};
+    use grimoire_dsp::envelope::EnvelopeFollower;
 
     #[test]
-    fn test_biquad_lowpass_dc_gain() {
-        let mut filter = Biquad::new();
-        // Set lowpass at 1kHz, Q=0.707, Sample rate 44.1kHz
-        filter.update(FilterType::LowPass, 1000.0, 0.707, 0.0, 44100.0);
-        
-        // Feed DC (1.0) for a while and check if it stabilizes near 1.0 (0dB gain at DC for LP)
-        let mut output = 0.0;
-        for _ in 0..1000 {
-            output = filter.process(1.0);
-        }
-        
-        assert!((output - 1.0).abs() < 1e-4, "LowPass DC gain should be close to 1.0, got {}", output);
+    fn test_mid_side_round_trip() {
+        // Mono content has no side, one-sided content splits evenly
+        assert_eq!(encode_mid_side([0.5, 0.5]), [0.5, 0.0]);
+        assert_eq!(encode_mid_side([1.0, 0.0]), [0.5, 0.5]);
+
+        for frame in [[0.3, -0.7], [1.0, 1.0], [-0.25, 0.0]] {
+            assert_eq!(decode_mid_side(encode_mid_side(frame)), frame);
+        }
     }
 
     #[test]
-    fn test_biquad_highpass_dc_rejection() {
-        let mut filter = Biquad::new();
-        // Set highpass at 1kHz
-        filter.update(FilterType::HighPass, 1000.0, 0.707, 0.0, 44100.0);
+    fn test_channel_modes_pick_channels() {
+        let filtered = |mode: ChannelMode| [mode.filters(0), mode.filters(1)];
+        assert_eq!(filtered(ChannelMode::Stereo), [true, true]);
+        assert_eq!(filtered(ChannelMode::Left), [true, false]);
+        assert_eq!(filtered(ChannelMode::Right), [false, true]);
+        assert_eq!(filtered(ChannelMode::MidSide), [true, true]);
+        assert_eq!(filtered(ChannelMode::Mid), [true, false]);
+        assert_eq!(filtered(ChannelMode::Side), [false, true]);
+        assert!(!ChannelMode::Left.is_mid_side());
+        assert!(ChannelMode::Side.is_mid_side());
+    }
+
+    #[test]
+    fn test_key_tracking_follows_notes() {
+        // At full tracking, a cutoff set to middle C plays the notes
+        for note in [36, 60, 69, 84] {
+            let freq = modulate_frequency(261.63, key_tracking_octaves(note, 1.0));
+            let expected = 440.0 * ((note as f32 - 69.0) / 12.0).exp2();
+            assert!(
+                (freq / expected - 1.0).abs() < 1e-4,
+                "Note {}: {} Hz",
+                note,
+                freq
+            );
+        }
+
+        // Half tracking moves half an octave per octave, and middle C never moves the cutoff
+        assert_eq!(key_tracking_octaves(84, 0.5), 1.0);
+        assert_eq!(key_tracking_octaves(60, 0.7), 0.0);
+    }
+
+    #[test]
+    fn test_envelope_moves_cutoff() {
+        let mut envelope = EnvelopeFollower::default();
+        envelope.set_times(1.0, 50.0, 44100.0);
+
+        // A full scale tone opens the filter by the whole depth
+        let mut level = 0.0;
+        for _ in 0..4410 {
+            level = envelope.process(1.0);
+        }
+        let opened = modulate_frequency(1000.0, 2.0 * level.min(1.0));
+        assert!((opened - 4000.0).abs() < 1.0, "Opened cutoff {}", opened);
+        let closed = modulate_frequency(1000.0, -2.0 * level.min(1.0));
+        assert!((closed - 250.0).abs() < 0.1, "Closed cutoff {}", closed);
 
-        // Feed DC (1.0) - should be rejected
-        let mut output = 0.0;
-        for _ in 0..1000 {
-            output = filter.process(1.0);
+        // After the release the cutoff is back where it started
+        for _ in 0..44100 {
+            level = envelope.process(0.0);
         }
+        let released = modulate_frequency(1000.0, 2.0 * level.min(1.0));
+        assert!(
+            (released - 1000.0).abs() < 1.0,
+            "Released cutoff {}",
+            released
+        );
 
-        assert!(output.abs() < 1e-4, "HighPass DC output should be close to 0.0, got {}", output);
+        // Deep modulation stays within the frequency range
+        assert_eq!(modulate_frequency(8000.0, 4.0), 20000.0);
+        assert_eq!(modulate_frequency(50.0, -4.0), 20.0);
     }
 }
@@ -1,15 +1,53 @@
 use nih_plug::prelude::*;
 
-use crate::dsp::coefficients::{BiquadCoefficients, FilterContext};
+use grimoire_dsp::filter_type::{Design, FilterType};
+use grimoire_dsp::lfo::{LfoShape, NoteDivision};
+use grimoire_dsp::oversampling::{OversamplingFactor, OversamplingPhase};
+use grimoire_dsp::prototype::Prototype;
 
 #[derive(Params)]
 pub struct CantripFilterParams {
+    /// Filter topology, which also decides whether Type or Morph picks the response
+    #[id = "engine"]
+    pub engine: EnumParam<FilterEngine>,
+
     #[id = "type"]
     pub filter_type: EnumParam<FilterType>,
 
+    /// Steepness of the low and high pass types
+    #[id = "slope"]
+    pub slope: EnumParam<Slope>,
+
+    /// How the biquad coefficients are derived, the analog matched design keeps high-frequency
+    /// curves from getting squashed towards Nyquist
+    #[id = "design"]
+    pub design: EnumParam<Design>,
+
+    /// Classic response of the Low Pass and High Pass types, everything but Butterworth ignores
+    /// the resonance and the design
+    #[id = "prototype"]
+    pub prototype: EnumParam<Prototype>,
+
+    /// Passband ripple of the Chebyshev I and elliptic prototypes
+    #[id = "ripple"]
+    pub ripple: FloatParam,
+
+    /// Stopband attenuation of the Chebyshev II and elliptic prototypes
+    #[id = "attenuation"]
+    pub attenuation: FloatParam,
+
+    /// Which channels get filtered, either as left and right or as mid and side
+    #[id = "channel_mode"]
+    pub channel_mode: EnumParam<ChannelMode>,
+
     #[id = "freq"]
     pub frequency: FloatParam,
 
+    /// Moves the cutoff of the left (or mid) channel down and the right (or side) channel up, by
+    /// half this many octaves each
+    #[id = "spread"]
+    pub spread: FloatParam,
+
     #[id = "q"]
     pub resonance: FloatParam,
 
@@ -17,378 +55,206 @@ pub struct CantripFilterParams {
     #[id = "filter_gain"]
     pub filter_gain: FloatParam,
 
-    /// Output gain
-    #[id = "gain"]
-    pub gain: FloatParam,
-}
+    /// Response of the state variable engine: 0 = low pass, 1 = band pass, 2 = high pass,
+    /// 3 = notch and 4 = peak, with crossfades in between
+    #[id = "morph"]
+    pub morph: FloatParam,
 
-#[derive(Enum, PartialEq, Clone, Copy, Debug)]
-pub enum FilterType {
-    // === Basic Filters (12dB/oct) ===
-    #[name = "Low Pass"]
-    LowPass,
-    #[name = "High Pass"]
-    HighPass,
-    #[name = "Band Pass"]
-    BandPass,
-    #[name = "Notch"]
-    Notch,
-    #[name = "All Pass"]
-    AllPass,
-
-    // === Gentle Slope (6dB/oct) ===
-    #[name = "Low Pass 6dB"]
-    LowPass6dB,
-    #[name = "High Pass 6dB"]
-    HighPass6dB,
-
-    // === EQ Types ===
-    #[name = "Peaking EQ"]
-    Peaking,
-    #[name = "Low Shelf"]
-    LowShelf,
-    #[name = "High Shelf"]
-    HighShelf,
-    #[name = "Tilt"]
-    Tilt,
-
-    // === Crossover (Linkwitz-Riley) ===
-    #[name = "LR Low Pass"]
-    LinkwitzRileyLP,
-    #[name = "LR High Pass"]
-    LinkwitzRileyHP,
-
-    // === Butterworth (maximally flat) ===
-    #[name = "Butterworth LP"]
-    ButterworthLP,
-    #[name = "Butterworth HP"]
-    ButterworthHP,
-
-    // === Band Pass Variations ===
-    #[name = "Band Pass 0dB"]
-    BandPass0dB,
-
-    // === Character / Creative ===
-    #[name = "Warmth"]
-    Warmth,
-    #[name = "Brightness"]
-    Brightness,
-    #[name = "Presence"]
-    Presence,
-    #[name = "Air"]
-    Air,
-    #[name = "Sub Bass"]
-    SubBass,
-    #[name = "Vocal"]
-    Vocal,
-
-    // === Utility ===
-    #[name = "DC Block"]
-    DCBlock,
-    #[name = "Unity"]
-    Unity,
-}
+    /// Input drive of the ladder engine (in dB), the output is scaled back down by the same
+    /// amount so only the saturation changes
+    #[id = "drive"]
+    pub drive: FloatParam,
 
-impl FilterType {
-    /// Compute biquad coefficients for this filter type.
-    pub fn compute_coefficients(
-        self,
-        freq: f32,
-        q: f32,
-        gain_db: f32,
-        sample_rate: f32,
-    ) -> BiquadCoefficients {
-        let ctx = FilterContext::new(freq, q, gain_db, sample_rate);
+    /// How far the input level moves the cutoff (in octaves at full scale), negative depths close
+    /// the filter instead of opening it
+    #[id = "env_depth"]
+    pub env_depth: FloatParam,
 
-        match self {
-            // Basic filters
-            Self::LowPass => Self::lowpass(&ctx),
-            Self::HighPass => Self::highpass(&ctx),
-            Self::BandPass => Self::bandpass(&ctx),
-            Self::Notch => Self::notch(&ctx),
-            Self::AllPass => Self::allpass(&ctx),
-
-            // Gentle slope (6dB/oct)
-            Self::LowPass6dB => Self::lowpass_6db(&ctx),
-            Self::HighPass6dB => Self::highpass_6db(&ctx),
-
-            // EQ types
-            Self::Peaking => Self::peaking(&ctx),
-            Self::LowShelf => Self::low_shelf(&ctx),
-            Self::HighShelf => Self::high_shelf(&ctx),
-            Self::Tilt => Self::tilt(&ctx, gain_db),
-
-            // Crossover (Linkwitz-Riley)
-            Self::LinkwitzRileyLP => Self::lowpass_with_q(&ctx, 0.5),
-            Self::LinkwitzRileyHP => Self::highpass_with_q(&ctx, 0.5),
-
-            // Butterworth
-            Self::ButterworthLP => Self::lowpass_with_q(&ctx, std::f32::consts::FRAC_1_SQRT_2),
-            Self::ButterworthHP => Self::highpass_with_q(&ctx, std::f32::consts::FRAC_1_SQRT_2),
-
-            // Band pass variations
-            Self::BandPass0dB => Self::bandpass_0db(&ctx, q),
-
-            // Character filters
-            Self::Warmth => Self::shelf_character(&ctx, gain_db, ShelfType::Low, 0.6),
-            Self::Brightness => Self::shelf_character(&ctx, gain_db, ShelfType::High, 0.7),
-            Self::Air => Self::shelf_character(&ctx, gain_db, ShelfType::High, 0.5),
-            Self::SubBass => Self::shelf_character(&ctx, gain_db, ShelfType::Low, 0.8),
-            Self::Presence => Self::peaking_character(&ctx, gain_db, 1.5),
-            Self::Vocal => Self::peaking_character(&ctx, gain_db, 2.0),
-
-            // Utility
-            Self::DCBlock => Self::dc_block(sample_rate),
-            Self::Unity => BiquadCoefficients::unity(),
-        }
-    }
+    #[id = "env_attack"]
+    pub env_attack: FloatParam,
 
-    // ========================================
-    // Basic Filters
-    // ========================================
-
-    fn lowpass(ctx: &FilterContext) -> BiquadCoefficients {
-        let b1 = 1.0 - ctx.cos_w0;
-        let b0 = b1 / 2.0;
-        BiquadCoefficients::from_raw(
-            b0,
-            b1,
-            b0,
-            1.0 + ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha,
-        )
-    }
+    #[id = "env_release"]
+    pub env_release: FloatParam,
 
-    fn highpass(ctx: &FilterContext) -> BiquadCoefficients {
-        let b1 = -(1.0 + ctx.cos_w0);
-        let b0 = (1.0 + ctx.cos_w0) / 2.0;
-        BiquadCoefficients::from_raw(
-            b0,
-            b1,
-            b0,
-            1.0 + ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha,
-        )
-    }
+    /// Follow the sidechain input instead of the main input
+    #[id = "env_sidechain"]
+    pub env_sidechain: BoolParam,
 
-    fn lowpass_with_q(ctx: &FilterContext, q: f32) -> BiquadCoefficients {
-        let alpha = ctx.alpha_with_q(q);
-        let b1 = 1.0 - ctx.cos_w0;
-        let b0 = b1 / 2.0;
-        BiquadCoefficients::from_raw(b0, b1, b0, 1.0 + alpha, -2.0 * ctx.cos_w0, 1.0 - alpha)
-    }
+    #[id = "lfo_shape"]
+    pub lfo_shape: EnumParam<LfoShape>,
 
-    fn highpass_with_q(ctx: &FilterContext, q: f32) -> BiquadCoefficients {
-        let alpha = ctx.alpha_with_q(q);
-        let b1 = -(1.0 + ctx.cos_w0);
-        let b0 = (1.0 + ctx.cos_w0) / 2.0;
-        BiquadCoefficients::from_raw(b0, b1, b0, 1.0 + alpha, -2.0 * ctx.cos_w0, 1.0 - alpha)
-    }
+    /// Lock the LFO to the host tempo, using Division instead of Rate
+    #[id = "lfo_sync"]
+    pub lfo_sync: BoolParam,
 
-    fn bandpass(ctx: &FilterContext) -> BiquadCoefficients {
-        BiquadCoefficients::from_raw(
-            ctx.alpha,
-            0.0,
-            -ctx.alpha,
-            1.0 + ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha,
-        )
-    }
+    #[id = "lfo_rate"]
+    pub lfo_rate: FloatParam,
 
-    fn bandpass_0db(ctx: &FilterContext, q: f32) -> BiquadCoefficients {
-        BiquadCoefficients::from_raw(
-            q * ctx.alpha,
-            0.0,
-            -q * ctx.alpha,
-            1.0 + ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha,
-        )
-    }
+    #[id = "lfo_division"]
+    pub lfo_division: EnumParam<NoteDivision>,
 
-    fn notch(ctx: &FilterContext) -> BiquadCoefficients {
-        BiquadCoefficients::from_raw(
-            1.0,
-            -2.0 * ctx.cos_w0,
-            1.0,
-            1.0 + ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha,
-        )
-    }
+    /// Phase of the right channel's LFO against the left one, sweeping the channels apart
+    #[id = "lfo_stereo_offset"]
+    pub lfo_stereo_offset: FloatParam,
 
-    fn allpass(ctx: &FilterContext) -> BiquadCoefficients {
-        BiquadCoefficients::from_raw(
-            1.0 - ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 + ctx.alpha,
-            1.0 + ctx.alpha,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha,
-        )
-    }
+    /// How far the LFO moves the cutoff (in octaves)
+    #[id = "lfo_cutoff_depth"]
+    pub lfo_cutoff_depth: FloatParam,
 
-    // ========================================
-    // 6dB/oct (1-pole approximation)
-    // ========================================
+    /// How far the LFO moves the resonance (in octaves)
+    #[id = "lfo_resonance_depth"]
+    pub lfo_resonance_depth: FloatParam,
 
-    fn lowpass_6db(ctx: &FilterContext) -> BiquadCoefficients {
-        let k = ctx.w0.tan() / 2.0;
-        let norm = 1.0 / (1.0 + k);
-        let b0 = k * norm;
-        BiquadCoefficients::from_raw(b0, b0, 0.0, 1.0, (k - 1.0) * norm, 0.0)
-    }
+    /// How closely the cutoff follows incoming MIDI notes. At 100% it moves an octave per
+    /// octave played, with Frequency as the cutoff for middle C.
+    #[id = "key_tracking"]
+    pub key_tracking: FloatParam,
 
-    fn highpass_6db(ctx: &FilterContext) -> BiquadCoefficients {
-        let k = ctx.w0.tan() / 2.0;
-        let norm = 1.0 / (1.0 + k);
-        BiquadCoefficients::from_raw(norm, -norm, 0.0, 1.0, (k - 1.0) * norm, 0.0)
-    }
+    /// How far the note envelope moves the cutoff (in octaves)
+    #[id = "note_env_depth"]
+    pub note_env_depth: FloatParam,
 
-    // ========================================
-    // EQ Types
-    // ========================================
-
-    fn peaking(ctx: &FilterContext) -> BiquadCoefficients {
-        BiquadCoefficients::from_raw(
-            1.0 + ctx.alpha * ctx.a,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha * ctx.a,
-            1.0 + ctx.alpha / ctx.a,
-            -2.0 * ctx.cos_w0,
-            1.0 - ctx.alpha / ctx.a,
-        )
-    }
+    #[id = "note_env_attack"]
+    pub note_env_attack: FloatParam,
 
-    fn low_shelf(ctx: &FilterContext) -> BiquadCoefficients {
-        Self::shelf_coefficients(ctx, ShelfType::Low)
-    }
+    #[id = "note_env_decay"]
+    pub note_env_decay: FloatParam,
 
-    fn high_shelf(ctx: &FilterContext) -> BiquadCoefficients {
-        Self::shelf_coefficients(ctx, ShelfType::High)
-    }
+    #[id = "note_env_sustain"]
+    pub note_env_sustain: FloatParam,
 
-    fn tilt(ctx: &FilterContext, gain_db: f32) -> BiquadCoefficients {
-        let tilt_a = 10.0f32.powf(gain_db / 20.0);
-        let sqrt_a = tilt_a.sqrt();
-        BiquadCoefficients::from_raw(
-            sqrt_a * (sqrt_a + ctx.alpha / sqrt_a),
-            -2.0 * sqrt_a * ctx.cos_w0,
-            sqrt_a * (sqrt_a - ctx.alpha / sqrt_a),
-            sqrt_a + ctx.alpha * sqrt_a,
-            -2.0 * sqrt_a * ctx.cos_w0,
-            sqrt_a - ctx.alpha * sqrt_a,
-        )
-    }
+    #[id = "note_env_release"]
+    pub note_env_release: FloatParam,
 
-    // ========================================
-    // Shelf Helpers
-    // ========================================
-
-    fn shelf_coefficients(ctx: &FilterContext, shelf_type: ShelfType) -> BiquadCoefficients {
-        let two_sqrt_a_alpha = 2.0 * ctx.a.sqrt() * ctx.alpha;
-
-        match shelf_type {
-            ShelfType::Low => BiquadCoefficients::from_raw(
-                ctx.a * ((ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
-                2.0 * ctx.a * ((ctx.a - 1.0) - (ctx.a + 1.0) * ctx.cos_w0),
-                ctx.a * ((ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
-                (ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
-                -2.0 * ((ctx.a - 1.0) + (ctx.a + 1.0) * ctx.cos_w0),
-                (ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
-            ),
-            ShelfType::High => BiquadCoefficients::from_raw(
-                ctx.a * ((ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
-                -2.0 * ctx.a * ((ctx.a - 1.0) + (ctx.a + 1.0) * ctx.cos_w0),
-                ctx.a * ((ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
-                (ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
-                2.0 * ((ctx.a - 1.0) - (ctx.a + 1.0) * ctx.cos_w0),
-                (ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
-            ),
-        }
-    }
+    /// Moves the filter gain with the level of the band instead of applying it all the time,
+    /// which turns Peaking EQ and the shelves into a dynamic EQ band
+    #[id = "dynamic"]
+    pub dynamic: BoolParam,
 
-    // ========================================
-    // Character Filters
-    // ========================================
-
-    fn shelf_character(
-        ctx: &FilterContext,
-        gain_db: f32,
-        shelf_type: ShelfType,
-        q: f32,
-    ) -> BiquadCoefficients {
-        let a = 10.0f32.powf(gain_db.max(3.0) / 40.0);
-        let alpha = ctx.alpha_with_q(q);
-        let two_sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
-
-        match shelf_type {
-            ShelfType::Low => BiquadCoefficients::from_raw(
-                a * ((a + 1.0) - (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
-                2.0 * a * ((a - 1.0) - (a + 1.0) * ctx.cos_w0),
-                a * ((a + 1.0) - (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
-                (a + 1.0) + (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
-                -2.0 * ((a - 1.0) + (a + 1.0) * ctx.cos_w0),
-                (a + 1.0) + (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
-            ),
-            ShelfType::High => BiquadCoefficients::from_raw(
-                a * ((a + 1.0) + (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
-                -2.0 * a * ((a - 1.0) + (a + 1.0) * ctx.cos_w0),
-                a * ((a + 1.0) + (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
-                (a + 1.0) - (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
-                2.0 * ((a - 1.0) - (a + 1.0) * ctx.cos_w0),
-                (a + 1.0) - (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
-            ),
-        }
-    }
+    /// Band level (in dB) above which the gain starts moving toward Filter Gain
+    #[id = "dyn_threshold"]
+    pub dyn_threshold: FloatParam,
 
-    fn peaking_character(ctx: &FilterContext, gain_db: f32, q: f32) -> BiquadCoefficients {
-        let a = 10.0f32.powf(gain_db.max(3.0) / 40.0);
-        let alpha = ctx.alpha_with_q(q);
-        BiquadCoefficients::from_raw(
-            1.0 + alpha * a,
-            -2.0 * ctx.cos_w0,
-            1.0 - alpha * a,
-            1.0 + alpha / a,
-            -2.0 * ctx.cos_w0,
-            1.0 - alpha / a,
-        )
+    /// How fast the gain moves once the band is over the threshold, as in a compressor
+    #[id = "dyn_ratio"]
+    pub dyn_ratio: FloatParam,
+
+    #[id = "dyn_attack"]
+    pub dyn_attack: FloatParam,
+
+    #[id = "dyn_release"]
+    pub dyn_release: FloatParam,
+
+    /// Listen to the sidechain input instead of the main input
+    #[id = "dyn_sidechain"]
+    pub dyn_sidechain: BoolParam,
+
+    /// Output gain
+    #[id = "gain"]
+    pub gain: FloatParam,
+
+    /// Runs the filters at a multiple of the host sample rate, which keeps high resonance and
+    /// fast modulation near the top of the spectrum clean
+    #[id = "oversampling"]
+    pub oversampling: EnumParam<OversamplingFactor>,
+
+    #[id = "oversampling_phase"]
+    pub oversampling_phase: EnumParam<OversamplingPhase>,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum FilterEngine {
+    /// Any of the filter types as a biquad
+    #[name = "Biquad"]
+    Biquad,
+    /// Zero-delay feedback SVF, morphing between its responses
+    #[name = "State Variable"]
+    StateVariable,
+    /// Saturating 4-pole transistor ladder low pass
+    #[name = "Ladder"]
+    Ladder,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum ChannelMode {
+    /// Left and right
+    #[name = "Stereo"]
+    Stereo,
+    #[name = "Left"]
+    Left,
+    #[name = "Right"]
+    Right,
+    /// Mid and side, each with its own cutoff once they're spread apart
+    #[name = "Mid/Side"]
+    MidSide,
+    #[name = "Mid"]
+    Mid,
+    #[name = "Side"]
+    Side,
+}
+
+impl ChannelMode {
+    /// Whether the filters see mid and side instead of left and right.
+    pub fn is_mid_side(self) -> bool {
+        matches!(self, Self::MidSide | Self::Mid | Self::Side)
     }
 
-    // ========================================
-    // Utility
-    // ========================================
-
-    fn dc_block(sample_rate: f32) -> BiquadCoefficients {
-        use std::f32::consts::PI;
-        let dc_w0 = 2.0 * PI * 20.0 / sample_rate;
-        let dc_cos = dc_w0.cos();
-        let dc_alpha = dc_w0.sin() / (2.0 * 0.707);
-        let b0 = (1.0 + dc_cos) / 2.0;
-        BiquadCoefficients::from_raw(
-            b0,
-            -(1.0 + dc_cos),
-            b0,
-            1.0 + dc_alpha,
-            -2.0 * dc_cos,
-            1.0 - dc_alpha,
-        )
+    /// Whether a channel (left or mid for 0, right or side for 1) gets filtered.
+    pub fn filters(self, channel_idx: usize) -> bool {
+        match self {
+            Self::Stereo | Self::MidSide => true,
+            Self::Left | Self::Mid => channel_idx == 0,
+            Self::Right | Self::Side => channel_idx == 1,
+        }
     }
 }
 
-#[derive(Clone, Copy)]
-enum ShelfType {
-    Low,
-    High,
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum Slope {
+    #[name = "12 dB/oct"]
+    Db12,
+    #[name = "24 dB/oct"]
+    Db24,
+    #[name = "36 dB/oct"]
+    Db36,
+    #[name = "48 dB/oct"]
+    Db48,
+}
+
+impl Slope {
+    /// Number of second-order sections needed for this slope.
+    pub fn sections(self) -> usize {
+        match self {
+            Self::Db12 => 1,
+            Self::Db24 => 2,
+            Self::Db36 => 3,
+            Self::Db48 => 4,
+        }
+    }
 }
 
 impl Default for CantripFilterParams {
     fn default() -> Self {
         Self {
+            engine: EnumParam::new("Engine", FilterEngine::Biquad),
             filter_type: EnumParam::new("Type", FilterType::LowPass),
+            slope: EnumParam::new("Slope", Slope::Db12),
+            design: EnumParam::new("Design", Design::Bilinear),
+            prototype: EnumParam::new("Prototype", Prototype::Butterworth),
+            ripple: FloatParam::new("Ripple", 1.0, FloatRange::Linear { min: 0.1, max: 6.0 })
+                .with_unit(" dB")
+                .with_step_size(0.1),
+            attenuation: FloatParam::new(
+                "Attenuation",
+                60.0,
+                FloatRange::Linear {
+                    min: 20.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_step_size(1.0),
+            channel_mode: EnumParam::new("Channel Mode", ChannelMode::Stereo),
             frequency: FloatParam::new(
                 "Frequency",
                 1000.0,
@@ -399,6 +265,18 @@ impl Default for CantripFilterParams {
                 },
             )
             .with_unit(" Hz")
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            spread: FloatParam::new(
+                "Spread",
+                0.0,
+                FloatRange::Linear {
+                    min: -4.0,
+                    max: 4.0,
+                },
+            )
+            .with_unit(" oct")
+            .with_smoother(SmoothingStyle::Linear(50.0))
             .with_value_to_string(formatters::v2s_f32_rounded(2)),
             resonance: FloatParam::new(
                 "Resonance",
@@ -409,6 +287,7 @@ impl Default for CantripFilterParams {
                     factor: FloatRange::skew_factor(0.5),
                 },
             )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
             .with_value_to_string(formatters::v2s_f32_rounded(2)),
             filter_gain: FloatParam::new(
                 "Filter Gain",
@@ -419,7 +298,212 @@ impl Default for CantripFilterParams {
                 },
             )
             .with_unit(" dB")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            morph: FloatParam::new("Morph", 0.0, FloatRange::Linear { min: 0.0, max: 4.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            drive: FloatParam::new(
+                "Drive",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_smoother(SmoothingStyle::Linear(50.0))
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            env_depth: FloatParam::new(
+                "Envelope Depth",
+                0.0,
+                FloatRange::Linear {
+                    min: -4.0,
+                    max: 4.0,
+                },
+            )
+            .with_unit(" oct")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            env_attack: FloatParam::new(
+                "Envelope Attack",
+                5.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(0.1),
+            env_release: FloatParam::new(
+                "Envelope Release",
+                150.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+            env_sidechain: BoolParam::new("Envelope Sidechain", false),
+            lfo_shape: EnumParam::new("LFO Shape", LfoShape::Sine),
+            lfo_sync: BoolParam::new("LFO Sync", false),
+            lfo_rate: FloatParam::new(
+                "LFO Rate",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            lfo_division: EnumParam::new("LFO Division", NoteDivision::Quarter),
+            lfo_stereo_offset: FloatParam::new(
+                "LFO Stereo Offset",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 360.0,
+                },
+            )
+            .with_unit("°")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            lfo_cutoff_depth: FloatParam::new(
+                "LFO Cutoff Depth",
+                0.0,
+                FloatRange::Linear {
+                    min: -4.0,
+                    max: 4.0,
+                },
+            )
+            .with_unit(" oct")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            lfo_resonance_depth: FloatParam::new(
+                "LFO Resonance Depth",
+                0.0,
+                FloatRange::Linear {
+                    min: -2.0,
+                    max: 2.0,
+                },
+            )
+            .with_unit(" oct")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            key_tracking: FloatParam::new(
+                "Key Tracking",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" %")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            note_env_depth: FloatParam::new(
+                "Note Env Depth",
+                0.0,
+                FloatRange::Linear {
+                    min: -4.0,
+                    max: 4.0,
+                },
+            )
+            .with_unit(" oct")
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            note_env_attack: FloatParam::new(
+                "Note Env Attack",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(0.1),
+            note_env_decay: FloatParam::new(
+                "Note Env Decay",
+                200.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 5000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+            note_env_sustain: FloatParam::new(
+                "Note Env Sustain",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            note_env_release: FloatParam::new(
+                "Note Env Release",
+                200.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 5000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+            dynamic: BoolParam::new("Dynamic", false),
+            dyn_threshold: FloatParam::new(
+                "Dynamic Threshold",
+                -20.0,
+                FloatRange::Linear {
+                    min: -60.0,
+                    max: 0.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_step_size(0.1),
+            dyn_ratio: FloatParam::new(
+                "Dynamic Ratio",
+                4.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(":1")
+            .with_step_size(0.1),
+            dyn_attack: FloatParam::new(
+                "Dynamic Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(0.1),
+            dyn_release: FloatParam::new(
+                "Dynamic Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+            dyn_sidechain: BoolParam::new("Dynamic Sidechain", false),
             gain: FloatParam::new(
                 "Gain",
                 util::db_to_gain(0.0),
@@ -433,6 +517,8 @@ impl Default for CantripFilterParams {
             .with_unit(" dB")
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            oversampling: EnumParam::new("Oversampling", OversamplingFactor::Off),
+            oversampling_phase: EnumParam::new("Oversampling Phase", OversamplingPhase::Linear),
         }
     }
 }
@@ -7,6 +7,8 @@ use std::sync::Arc;
 
 struct CantripGain {
     params: Arc<CantripGainParams>,
+    /// Whether the mono input needs to be copied to the second output channel
+    mono_to_stereo: bool,
 }
 
 #[derive(Params)]
@@ -27,6 +29,7 @@ impl Default for CantripGain {
     fn default() -> Self {
         Self {
             params: Arc::new(CantripGainParams::default()),
+            mono_to_stereo: false,
         }
     }
 }
@@ -79,18 +82,40 @@ impl Plugin for CantripGain {
 
     // The first audio IO layout is used as the default. The other layouts may be selected either
     // explicitly or automatically by the host or the user depending on the plugin API/backend.
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
-
-        aux_input_ports: &[],
-        aux_output_ports: &[],
-
-        // Individual ports and the layout as a whole can be named here. By default these names
-        // are generated as needed. This layout will be called 'Stereo', while a layout with
-        // only one input and output channel would be called 'Mono'.
-        names: PortNames::const_default(),
-    }];
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+
+            // Individual ports and the layout as a whole can be named here. By default these names
+            // are generated as needed. This layout will be called 'Stereo', while a layout with
+            // only one input and output channel would be called 'Mono'.
+            names: PortNames::const_default(),
+        },
+        // A mono source panned into the stereo field
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(2),
+
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+
+            names: PortNames::const_default(),
+        },
+        // Plain mono, where only the gain applies
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+
+            names: PortNames::const_default(),
+        },
+    ];
 
 
     const MIDI_INPUT: MidiConfig = MidiConfig::None;
@@ -113,13 +138,15 @@ impl Plugin for CantripGain {
 
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
+        audio_io_layout: &AudioIOLayout,
         _buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
         // Resize buffers and perform other potentially expensive initialization operations here.
         // The `reset()` function is always called right after this function. You can remove this
         // function if you do not need it.
+        self.mono_to_stereo = audio_io_layout.main_input_channels == NonZeroU32::new(1)
+            && audio_io_layout.main_output_channels == NonZeroU32::new(2);
         true
     }
 
@@ -134,7 +161,7 @@ impl Plugin for CantripGain {
         _aux: &mut AuxiliaryBuffers,
         _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for channel_samples in buffer.iter_samples() {
+        for mut channel_samples in buffer.iter_samples() {
             // Smoothing is optionally built into the parameters themselves
             let gain = self.params.gain.smoothed.next();
             let pan = self.params.pan.smoothed.next();
@@ -146,27 +173,27 @@ impl Plugin for CantripGain {
             let pan_l = pan_angle.cos();
             let pan_r = pan_angle.sin();
 
-            // We assume stereo output.
-            // If input is mono, we might need to handle it, but AUDIO_IO_LAYOUTS says 2 channels.
-            // buffer.iter_samples() yields iterators over channels.
-            // But we need to process L and R differently.
-            // iter_samples yields *frames*? No, let's check docs or usage.
-            // nih-plug docs: buffer.iter_samples() returns an iterator that yields `&mut [f32]`.
-            // The slice length is the number of channels.
-            // So `channel_samples` is effectively `&mut [f32]` where len == num_channels.
-            
-            let samples = channel_samples.into_iter();
-            let mut samples_iter = samples;
-
-            if let (Some(l), Some(r)) = (samples_iter.next(), samples_iter.next()) {
-                let in_l = *l;
-                let in_r = *r;
-
-                *l = in_l * gain * pan_l;
-                *r = in_r * gain * pan_r;
+            if self.mono_to_stereo {
+                // Only the first channel holds input, the second one is ours to fill
+                let mono = channel_samples.get_mut(0).map_or(0.0, |sample| *sample);
+                if let Some(right) = channel_samples.get_mut(1) {
+                    *right = mono;
+                }
+            }
+
+            // `channel_samples` holds one sample per channel, so it has one or two elements
+            // depending on the layout the host picked
+            let mut samples_iter = channel_samples.iter_mut();
+
+            match (samples_iter.next(), samples_iter.next()) {
+                (Some(l), Some(r)) => {
+                    *l *= gain * pan_l;
+                    *r *= gain * pan_r;
+                }
+                // There is nothing to pan with a single output channel
+                (Some(mono), None) => *mono *= gain,
+                _ => (),
             }
-            // If there are more channels, we ignore them or process them? 
-            // For now, let's assume stereo.
         }
 
         ProcessStatus::Normal
@@ -180,7 +207,11 @@ impl ClapPlugin for CantripGain {
     const CLAP_SUPPORT_URL: Option<&'static str> = None;
 
     // Don't forget to change these features
-    const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect, ClapFeature::Stereo];
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Stereo,
+        ClapFeature::Mono,
+    ];
 }
 
 impl Vst3Plugin for CantripGain {
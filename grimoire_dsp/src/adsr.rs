@@ -0,0 +1,171 @@
+/// Attack, decay, sustain, release envelope triggered by notes.
+///
+/// The attack is a linear ramp, decay and release are one-pole curves like the
+/// [`EnvelopeFollower`](crate::envelope::EnvelopeFollower). Retriggering starts the attack from
+/// the current level, so fast notes don't click.
+#[derive(Clone, Copy, Debug)]
+pub struct Adsr {
+    stage: Stage,
+    level: f32,
+    attack_step: f32,
+    decay_coeff: f32,
+    sustain: f32,
+    release_coeff: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Release,
+}
+
+/// Level below which a release counts as finished.
+const SILENCE: f32 = 1e-5;
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self {
+            stage: Stage::Idle,
+            level: 0.0,
+            attack_step: 1.0,
+            decay_coeff: 0.0,
+            sustain: 1.0,
+            release_coeff: 0.0,
+        }
+    }
+}
+
+impl Adsr {
+    /// Reset the envelope to silence.
+    pub fn reset(&mut self) {
+        self.stage = Stage::Idle;
+        self.level = 0.0;
+    }
+
+    /// Update the stage times and the sustain level.
+    ///
+    /// # Arguments
+    /// * `attack_ms` - Time for the ramp from zero to full level in milliseconds
+    /// * `decay_ms` - Time constant of the fall to the sustain level in milliseconds
+    /// * `sustain` - Level held while the note is down, from 0 to 1
+    /// * `release_ms` - Time constant of the fall to zero after the note ends in milliseconds
+    /// * `sample_rate` - Sample rate in Hz
+    pub fn set_parameters(
+        &mut self,
+        attack_ms: f32,
+        decay_ms: f32,
+        sustain: f32,
+        release_ms: f32,
+        sample_rate: f32,
+    ) {
+        self.attack_step = 1.0 / (attack_ms * 0.001 * sample_rate).max(1.0);
+        self.decay_coeff = (-1.0 / (decay_ms * 0.001 * sample_rate)).exp();
+        self.sustain = sustain;
+        self.release_coeff = (-1.0 / (release_ms * 0.001 * sample_rate)).exp();
+    }
+
+    /// Start the attack from the current level.
+    pub fn note_on(&mut self) {
+        self.stage = Stage::Attack;
+    }
+
+    /// Start the release from the current level.
+    pub fn note_off(&mut self) {
+        if self.stage != Stage::Idle {
+            self.stage = Stage::Release;
+        }
+    }
+
+    /// Advance by one sample and return the current level.
+    pub fn process(&mut self) -> f32 {
+        match self.stage {
+            Stage::Idle => (),
+            Stage::Attack => {
+                self.level += self.attack_step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level = self.sustain + self.decay_coeff * (self.level - self.sustain);
+            }
+            Stage::Release => {
+                self.level *= self.release_coeff;
+                if self.level < SILENCE {
+                    self.level = 0.0;
+                    self.stage = Stage::Idle;
+                }
+            }
+        }
+
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adsr_stages() {
+        let mut adsr = Adsr::default();
+        adsr.set_parameters(10.0, 50.0, 0.5, 100.0, 1000.0);
+        assert_eq!(adsr.process(), 0.0);
+
+        // Linear attack reaches the top after exactly the attack time
+        adsr.note_on();
+        for _ in 0..5 {
+            adsr.process();
+        }
+        assert!((adsr.process() - 0.6).abs() < 1e-5);
+        for _ in 0..4 {
+            adsr.process();
+        }
+        assert_eq!(adsr.level, 1.0);
+
+        // Decay settles on the sustain level and stays there
+        for _ in 0..1000 {
+            adsr.process();
+        }
+        assert!((adsr.process() - 0.5).abs() < 1e-5);
+
+        // One release time constant later the level has dropped by 1/e
+        adsr.note_off();
+        for _ in 0..99 {
+            adsr.process();
+        }
+        let level = adsr.process();
+        assert!(
+            (level - 0.5 / std::f32::consts::E).abs() < 1e-3,
+            "{}",
+            level
+        );
+        for _ in 0..2000 {
+            adsr.process();
+        }
+        assert_eq!(adsr.process(), 0.0);
+        assert_eq!(adsr.stage, Stage::Idle);
+    }
+
+    #[test]
+    fn test_adsr_retrigger_starts_from_current_level() {
+        let mut adsr = Adsr::default();
+        adsr.set_parameters(10.0, 50.0, 0.5, 100.0, 1000.0);
+        adsr.note_on();
+        for _ in 0..100 {
+            adsr.process();
+        }
+        adsr.note_off();
+        for _ in 0..10 {
+            adsr.process();
+        }
+
+        let before = adsr.level;
+        adsr.note_on();
+        let after = adsr.process();
+        assert!((after - before - 0.1).abs() < 1e-5);
+    }
+}
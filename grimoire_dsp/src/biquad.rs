@@ -0,0 +1,163 @@
+use crate::coefficients::BiquadCoefficients;
+use crate::filter_type::FilterType;
+
+/// A biquad (two-pole, two-zero) digital filter.
+///
+/// Implemented in Transposed Direct Form II:
+/// y[n] = b0*x[n] + s1
+/// s1 = b1*x[n] - a1*y[n] + s2
+/// s2 = b2*x[n] - a2*y[n]
+///
+/// The two state variables hold partial sums rather than past samples, which keeps the filter
+/// well behaved when the coefficients change from one sample to the next.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Biquad {
+    // Coefficients
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    // State
+    s1: f32,
+    s2: f32,
+}
+
+impl Biquad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the filter state to zero.
+    pub fn reset(&mut self) {
+        self.s1 = 0.0;
+        self.s2 = 0.0;
+    }
+
+    /// Update filter coefficients for the given filter type and parameters.
+    pub fn update(
+        &mut self,
+        filter_type: FilterType,
+        freq: f32,
+        q: f32,
+        gain_db: f32,
+        sample_rate: f32,
+    ) {
+        let coeffs = filter_type.compute_coefficients(freq, q, gain_db, sample_rate);
+        self.set_coefficients(coeffs);
+    }
+
+    /// Directly set the filter coefficients.
+    pub fn set_coefficients(&mut self, coeffs: BiquadCoefficients) {
+        self.b0 = coeffs.b0;
+        self.b1 = coeffs.b1;
+        self.b2 = coeffs.b2;
+        self.a1 = coeffs.a1;
+        self.a2 = coeffs.a2;
+    }
+
+    /// The current filter coefficients.
+    pub fn coefficients(&self) -> BiquadCoefficients {
+        BiquadCoefficients {
+            b0: self.b0,
+            b1: self.b1,
+            b2: self.b2,
+            a1: self.a1,
+            a2: self.a2,
+        }
+    }
+
+    /// Process a single sample through the filter.
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.s1;
+
+        self.s1 = self.b1 * input - self.a1 * output + self.s2;
+        self.s2 = self.b2 * input - self.a2 * output;
+
+        // Anti-denormal: flush very small values to zero.
+        // This prevents CPU spikes when the signal decays to subnormal values.
+        if self.s1.abs() < 1e-15 {
+            self.s1 = 0.0;
+        }
+        if self.s2.abs() < 1e-15 {
+            self.s2 = 0.0;
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_biquad_lowpass_dc_gain() {
+        let mut filter = Biquad::new();
+        // Set lowpass at 1kHz, Q=0.707, Sample rate 44.1kHz
+        filter.update(FilterType::LowPass, 1000.0, 0.707, 0.0, 44100.0);
+
+        // Feed DC (1.0) for a while and check if it stabilizes near 1.0 (0dB gain at DC for LP)
+        let mut output = 0.0;
+        for _ in 0..1000 {
+            output = filter.process(1.0);
+        }
+
+        assert!(
+            (output - 1.0).abs() < 1e-4,
+            "LowPass DC gain should be close to 1.0, got {}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_biquad_highpass_dc_rejection() {
+        let mut filter = Biquad::new();
+        // Set highpass at 1kHz
+        filter.update(FilterType::HighPass, 1000.0, 0.707, 0.0, 44100.0);
+
+        // Feed DC (1.0) - should be rejected
+        let mut output = 0.0;
+        for _ in 0..1000 {
+            output = filter.process(1.0);
+        }
+
+        assert!(
+            output.abs() < 1e-4,
+            "HighPass DC output should be close to 0.0, got {}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_biquad_stable_under_fast_modulation() {
+        let mut filter = Biquad::new();
+
+        // Sweep a resonant low pass across the whole range every 100 samples
+        let mut peak = 0.0f32;
+        for i in 0..44100 {
+            let sweep = (i as f32 * std::f32::consts::TAU / 100.0).sin();
+            let freq = 20.0 * 1000.0f32.powf(0.5 + 0.5 * sweep);
+            filter.update(FilterType::LowPass, freq, 10.0, 0.0, 44100.0);
+
+            let input = if i % 2 == 0 { 1.0 } else { -1.0 };
+            peak = peak.max(filter.process(input).abs());
+        }
+
+        assert!(
+            peak.is_finite() && peak < 100.0,
+            "Filter blew up, peak {}",
+            peak
+        );
+    }
+
+    #[test]
+    fn test_biquad_coefficients_round_trip() {
+        let coeffs = FilterType::Peaking.compute_coefficients(1000.0, 2.0, 6.0, 44100.0);
+        let mut filter = Biquad::new();
+        filter.set_coefficients(coeffs);
+
+        let fields = |c: BiquadCoefficients| [c.b0, c.b1, c.b2, c.a1, c.a2];
+        assert_eq!(fields(filter.coefficients()), fields(coeffs));
+    }
+}
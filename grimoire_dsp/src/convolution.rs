@@ -0,0 +1,258 @@
+//! Uniformly partitioned FFT convolution.
+//!
+//! The kernel is split into partitions of one block each. Every block of input is transformed
+//! once, and the output is the sum of the last input spectra multiplied with the matching
+//! partition spectra (overlap-save). This keeps long FIR filters affordable at a latency of a
+//! single block.
+//!
+//! The kernel is real, so the two channels of a stereo signal are convolved together as the real
+//! and imaginary parts of one complex signal.
+
+use std::mem;
+
+use crate::fft::{Complex, Fft};
+
+#[derive(Clone, Debug)]
+pub struct PartitionedConvolver {
+    fft: Fft,
+    block_size: usize,
+    partitions: usize,
+    /// Partition spectra, one after the other
+    kernel: Vec<Complex>,
+    /// Spectra of the kernel that is being faded out
+    previous: Vec<Complex>,
+    /// Spectra of a kernel that takes over at the start of the next block
+    pending: Vec<Complex>,
+    has_pending: bool,
+    /// Spectra of the most recent input blocks, a ring buffer indexed by `newest`
+    history: Vec<Complex>,
+    newest: usize,
+    /// The previous and the current input block
+    input: Vec<Complex>,
+    /// Output for the current block, computed at the end of the previous one
+    output: Vec<Complex>,
+    /// Spectra being summed and transformed back
+    scratch: Vec<Complex>,
+    position: usize,
+}
+
+impl PartitionedConvolver {
+    /// Create a convolver for kernels of up to `max_kernel_len` taps, processed in blocks of
+    /// `block_size` samples (a power of two).
+    pub fn new(block_size: usize, max_kernel_len: usize) -> Self {
+        let fft_size = 2 * block_size;
+        let partitions = max_kernel_len.div_ceil(block_size).max(1);
+
+        Self {
+            fft: Fft::new(fft_size),
+            block_size,
+            partitions,
+            kernel: vec![Complex::ZERO; partitions * fft_size],
+            previous: vec![Complex::ZERO; partitions * fft_size],
+            pending: vec![Complex::ZERO; partitions * fft_size],
+            has_pending: false,
+            history: vec![Complex::ZERO; partitions * fft_size],
+            newest: 0,
+            input: vec![Complex::ZERO; fft_size],
+            output: vec![Complex::ZERO; block_size],
+            scratch: vec![Complex::ZERO; fft_size],
+            position: 0,
+        }
+    }
+
+    /// Latency in samples, the convolver has to collect a whole block before it can transform it.
+    pub fn latency(&self) -> u32 {
+        self.block_size as u32
+    }
+
+    /// Clear the signal history. The kernel is kept.
+    pub fn reset(&mut self) {
+        self.history.fill(Complex::ZERO);
+        self.input.fill(Complex::ZERO);
+        self.output.fill(Complex::ZERO);
+        self.previous.fill(Complex::ZERO);
+        self.position = 0;
+    }
+
+    /// Whether a kernel from [`set_kernel()`](Self::set_kernel) is still waiting for the next
+    /// block. Setting another one replaces it.
+    pub fn kernel_pending(&self) -> bool {
+        self.has_pending
+    }
+
+    /// Switch to a new kernel at the start of the next block, crossfading from the old one over
+    /// that block. Taps beyond the maximum kernel length are ignored.
+    pub fn set_kernel(&mut self, kernel: &[f32]) {
+        for partition in 0..self.partitions {
+            self.prepare_partition(kernel, partition);
+        }
+        self.commit_kernel();
+    }
+
+    /// Number of partitions the kernel is split into.
+    pub fn partitions(&self) -> usize {
+        self.partitions
+    }
+
+    /// Transform one partition of a new kernel, so the work of
+    /// [`set_kernel()`](Self::set_kernel) can be spread over several calls. This withdraws a
+    /// kernel that is still pending. Once every partition is prepared,
+    /// [`commit_kernel()`](Self::commit_kernel) switches to it.
+    pub fn prepare_partition(&mut self, kernel: &[f32], partition: usize) {
+        let fft_size = 2 * self.block_size;
+        let spectrum = &mut self.pending[partition * fft_size..(partition + 1) * fft_size];
+
+        // Each partition is zero padded to twice its length
+        spectrum.fill(Complex::ZERO);
+        let taps = kernel.iter().skip(partition * self.block_size);
+        for (value, &tap) in spectrum.iter_mut().zip(taps).take(self.block_size) {
+            *value = Complex::new(tap, 0.0);
+        }
+        self.fft.forward(spectrum);
+        self.has_pending = false;
+    }
+
+    /// Switch to the kernel from [`prepare_partition()`](Self::prepare_partition) at the start
+    /// of the next block, like [`set_kernel()`](Self::set_kernel).
+    pub fn commit_kernel(&mut self) {
+        self.has_pending = true;
+    }
+
+    /// Process one sample of each channel.
+    pub fn process(&mut self, left: f32, right: f32) -> [f32; 2] {
+        self.input[self.block_size + self.position] = Complex::new(left, right);
+        let output = self.output[self.position];
+
+        self.position += 1;
+        if self.position == self.block_size {
+            self.position = 0;
+            self.process_block();
+        }
+
+        [output.re, output.im]
+    }
+
+    fn process_block(&mut self) {
+        let fft_size = 2 * self.block_size;
+
+        // Transform the last two blocks into the newest history slot
+        self.newest = (self.newest + 1) % self.partitions;
+        let slot = &mut self.history[self.newest * fft_size..(self.newest + 1) * fft_size];
+        slot.copy_from_slice(&self.input);
+        self.fft.forward(slot);
+        self.input.copy_within(self.block_size.., 0);
+
+        let fading = self.has_pending;
+        if fading {
+            mem::swap(&mut self.previous, &mut self.kernel);
+            mem::swap(&mut self.kernel, &mut self.pending);
+            self.has_pending = false;
+        }
+
+        // The second half of the result is free of wraparound
+        self.convolve_history(false);
+        let (_, tail) = self.scratch.split_at(self.block_size);
+        self.output.copy_from_slice(tail);
+
+        if fading {
+            self.convolve_history(true);
+            let (_, tail) = self.scratch.split_at(self.block_size);
+            for (i, (output, old)) in self.output.iter_mut().zip(tail).enumerate() {
+                let t = (i + 1) as f32 / self.block_size as f32;
+                *output = *output * t + *old * (1.0 - t);
+            }
+        }
+    }
+
+    /// Sum the input history multiplied with the kernel (or the previous kernel) into the
+    /// scratch buffer, and transform it back.
+    fn convolve_history(&mut self, previous: bool) {
+        let fft_size = 2 * self.block_size;
+        let kernel = if previous {
+            &self.previous
+        } else {
+            &self.kernel
+        };
+
+        self.scratch.fill(Complex::ZERO);
+        for (partition, spectrum) in kernel.chunks_exact(fft_size).enumerate() {
+            // Partition p applies to the input from p blocks ago
+            let slot = (self.newest + self.partitions - partition) % self.partitions;
+            let input = &self.history[slot * fft_size..(slot + 1) * fft_size];
+            for ((sum, &x), &h) in self.scratch.iter_mut().zip(input).zip(spectrum) {
+                *sum = *sum + x * h;
+            }
+        }
+        self.fft.inverse(&mut self.scratch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kernel(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (i as f32 * 0.7).sin() * (-(i as f32) / 40.0).exp())
+            .collect()
+    }
+
+    fn signal(i: usize) -> [f32; 2] {
+        [(i as f32 * 0.13).sin(), (i as f32 * 0.41).cos() * 0.5]
+    }
+
+    #[test]
+    fn test_convolver_matches_direct_convolution() {
+        let kernel = kernel(100);
+        let mut convolver = PartitionedConvolver::new(16, kernel.len());
+        convolver.set_kernel(&kernel);
+        let latency = convolver.latency() as usize;
+
+        let outputs: Vec<[f32; 2]> = (0..400)
+            .map(|i| {
+                let [left, right] = signal(i);
+                convolver.process(left, right)
+            })
+            .collect();
+
+        // The first block fades the kernel in from silence
+        for (i, output) in outputs.iter().enumerate().skip(2 * latency) {
+            for (channel, actual) in output.iter().enumerate() {
+                let expected: f32 = (0..kernel.len())
+                    .filter(|tap| *tap <= i - latency)
+                    .map(|tap| kernel[tap] * signal(i - latency - tap)[channel])
+                    .sum();
+                assert!(
+                    (actual - expected).abs() < 1e-4,
+                    "Sample {} channel {}: {} != {}",
+                    i,
+                    channel,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_convolver_crossfades_kernel_changes() {
+        let mut convolver = PartitionedConvolver::new(16, 32);
+        convolver.set_kernel(&[1.0]);
+        for _ in 0..64 {
+            convolver.process(1.0, 1.0);
+        }
+        assert!(!convolver.kernel_pending());
+
+        // The new kernel waits for the next block, then takes over within one block
+        convolver.set_kernel(&[0.0, 2.0]);
+        assert!(convolver.kernel_pending());
+        let outputs: Vec<f32> = (0..64).map(|_| convolver.process(1.0, 1.0)[0]).collect();
+        assert_eq!(outputs[..16], [1.0; 16]);
+        for pair in outputs[16..32].windows(2) {
+            assert!(pair[1] >= pair[0] - 1e-6, "Crossfade isn't monotonic");
+        }
+        for output in &outputs[32..] {
+            assert!((output - 2.0).abs() < 1e-5);
+        }
+    }
+}
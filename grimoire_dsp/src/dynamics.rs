@@ -0,0 +1,67 @@
+//! Static gain curves shared by the compressor and the dynamic EQ.
+
+/// Level in dB of a linear envelope, with a floor to avoid -inf.
+pub fn level_db(envelope: f32) -> f32 {
+    if envelope > 1e-10 {
+        20.0 * envelope.log10()
+    } else {
+        -100.0
+    }
+}
+
+/// Compute gain reduction in dB for a given input level.
+///
+/// # Arguments
+/// * `input_db` - Input level in dB
+/// * `threshold_db` - Threshold in dB
+/// * `ratio` - Compression ratio (e.g., 4.0 for 4:1)
+/// * `knee_db` - Knee width in dB (0 = hard knee)
+///
+/// # Returns
+/// Gain reduction in dB (negative value)
+pub fn gain_reduction_db(input_db: f32, threshold_db: f32, ratio: f32, knee_db: f32) -> f32 {
+    let half_knee = knee_db / 2.0;
+
+    if knee_db > 0.0
+        && input_db > (threshold_db - half_knee)
+        && input_db < (threshold_db + half_knee)
+    {
+        // Soft knee region
+        let x = input_db - threshold_db + half_knee;
+        (1.0 / ratio - 1.0) * x * x / (2.0 * knee_db)
+    } else if input_db >= threshold_db + half_knee {
+        // Above knee - full compression
+        let excess = input_db - threshold_db;
+        let compressed_excess = excess / ratio;
+        compressed_excess - excess
+    } else {
+        // Below threshold - no compression
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gain_reduction_curve() {
+        // Nothing below the threshold, the excess divided by the ratio above it
+        assert_eq!(gain_reduction_db(-30.0, -20.0, 4.0, 0.0), 0.0);
+        assert_eq!(gain_reduction_db(-12.0, -20.0, 4.0, 0.0), -6.0);
+
+        // The soft knee meets both straight parts without a step
+        for edge in [-23.0, -17.0] {
+            let inside = gain_reduction_db(edge - 1e-3, -20.0, 4.0, 6.0);
+            let outside = gain_reduction_db(edge + 1e-3, -20.0, 4.0, 6.0);
+            assert!((inside - outside).abs() < 1e-2, "Step at {}dB", edge);
+        }
+    }
+
+    #[test]
+    fn test_level_db_floor() {
+        assert_eq!(level_db(1.0), 0.0);
+        assert!((level_db(0.5) + 6.0206).abs() < 1e-3);
+        assert_eq!(level_db(0.0), -100.0);
+    }
+}
@@ -0,0 +1,195 @@
+//! In-place radix-2 FFT.
+//!
+//! The twiddle factors and the bit reversal permutation are computed up front, so transforms
+//! don't allocate and can run on the audio thread.
+
+use std::f64::consts::TAU;
+
+use crate::complex;
+
+pub type Complex = complex::Complex<f32>;
+
+#[derive(Clone, Debug)]
+pub struct Fft {
+    /// `e^(-j 2pi k / size)` for the first half of the circle
+    twiddles: Vec<Complex>,
+    /// Index every input ends up at after the permutation
+    bit_reverse: Vec<usize>,
+}
+
+impl Fft {
+    /// Prepare a transform of `size` points, which must be a power of two.
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "FFT size must be a power of two");
+
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let w = TAU * k as f64 / size as f64;
+                Complex::new(w.cos() as f32, -w.sin() as f32)
+            })
+            .collect();
+        let bits = size.trailing_zeros();
+        let bit_reverse = (0..size)
+            .map(|i| {
+                i.reverse_bits()
+                    .checked_shr(usize::BITS - bits)
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        Self {
+            twiddles,
+            bit_reverse,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.bit_reverse.len()
+    }
+
+    /// Forward transform, without scaling.
+    pub fn forward(&self, data: &mut [Complex]) {
+        self.transform(data, false);
+    }
+
+    /// Inverse transform, scaled by `1 / size` so it undoes [`forward()`](Self::forward).
+    pub fn inverse(&self, data: &mut [Complex]) {
+        self.transform(data, true);
+        self.scale(data);
+    }
+
+    /// Number of calls to [`inverse_step()`](Self::inverse_step) a whole inverse transform takes.
+    pub fn inverse_steps(&self) -> usize {
+        self.size().trailing_zeros() as usize + 1
+    }
+
+    /// Run one step of the inverse transform, so a large transform can be spread over several
+    /// calls. Step 0 permutes the input and every later step is one pass of butterflies, the last
+    /// one scaled. Running all of them in order gives the same result as
+    /// [`inverse()`](Self::inverse).
+    pub fn inverse_step(&self, data: &mut [Complex], step: usize) {
+        assert_eq!(data.len(), self.size());
+
+        if step == 0 {
+            self.permute(data);
+        } else {
+            self.butterflies(data, 1 << (step - 1), true);
+        }
+        if step + 1 == self.inverse_steps() {
+            self.scale(data);
+        }
+    }
+
+    fn transform(&self, data: &mut [Complex], inverse: bool) {
+        let size = self.size();
+        assert_eq!(data.len(), size);
+
+        self.permute(data);
+
+        // Butterflies, doubling the length of the sub-transforms every pass
+        let mut half = 1;
+        while half < size {
+            self.butterflies(data, half, inverse);
+            half *= 2;
+        }
+    }
+
+    fn permute(&self, data: &mut [Complex]) {
+        for (i, &j) in self.bit_reverse.iter().enumerate() {
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+    }
+
+    /// One pass of butterflies, combining sub-transforms of length `half`.
+    fn butterflies(&self, data: &mut [Complex], half: usize, inverse: bool) {
+        let size = self.size();
+        let stride = size / (2 * half);
+        for start in (0..size).step_by(2 * half) {
+            for k in 0..half {
+                let twiddle = self.twiddles[k * stride];
+                let twiddle = if inverse { twiddle.conj() } else { twiddle };
+                let even = data[start + k];
+                let odd = data[start + k + half] * twiddle;
+                data[start + k] = even + odd;
+                data[start + k + half] = even - odd;
+            }
+        }
+    }
+
+    fn scale(&self, data: &mut [Complex]) {
+        let scale = 1.0 / self.size() as f32;
+        for value in data.iter_mut() {
+            *value = *value * scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Direct evaluation of the DFT, for comparison.
+    fn dft(input: &[Complex]) -> Vec<Complex> {
+        let size = input.len();
+        (0..size)
+            .map(|k| {
+                input
+                    .iter()
+                    .enumerate()
+                    .fold(Complex::ZERO, |sum, (n, value)| {
+                        let w = TAU * (k * n % size) as f64 / size as f64;
+                        sum + *value * Complex::new(w.cos() as f32, -w.sin() as f32)
+                    })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_matches_dft() {
+        for size in [1, 2, 8, 64] {
+            let fft = Fft::new(size);
+            let input: Vec<Complex> = (0..size)
+                .map(|i| Complex::new((i as f32 * 0.37).sin(), (i as f32 * 1.3).cos()))
+                .collect();
+
+            let mut output = input.clone();
+            fft.forward(&mut output);
+            for (actual, expected) in output.iter().zip(dft(&input)) {
+                assert!(
+                    (actual.re - expected.re).abs() < 1e-4
+                        && (actual.im - expected.im).abs() < 1e-4,
+                    "Size {}: {:?} != {:?}",
+                    size,
+                    actual,
+                    expected
+                );
+            }
+
+            fft.inverse(&mut output);
+            for (actual, expected) in output.iter().zip(&input) {
+                assert!((actual.re - expected.re).abs() < 1e-5);
+                assert!((actual.im - expected.im).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fft_inverse_in_steps() {
+        for size in [1, 2, 64] {
+            let fft = Fft::new(size);
+            let input: Vec<Complex> = (0..size)
+                .map(|i| Complex::new((i as f32 * 0.37).sin(), (i as f32 * 1.3).cos()))
+                .collect();
+
+            let mut expected = input.clone();
+            fft.inverse(&mut expected);
+            let mut stepped = input.clone();
+            for step in 0..fft.inverse_steps() {
+                fft.inverse_step(&mut stepped, step);
+            }
+            assert_eq!(stepped, expected, "Size {}", size);
+        }
+    }
+}
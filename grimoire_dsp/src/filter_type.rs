@@ -0,0 +1,438 @@
+use nih_plug::prelude::*;
+
+use crate::coefficients::{BiquadCoefficients, FilterContext};
+use crate::matched;
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum FilterType {
+    // === Basic Filters (12dB/oct) ===
+    #[name = "Low Pass"]
+    LowPass,
+    #[name = "High Pass"]
+    HighPass,
+    /// Peaks at 0dB at any Q
+    #[name = "Band Pass"]
+    BandPass,
+    #[name = "Notch"]
+    Notch,
+    #[name = "All Pass"]
+    AllPass,
+
+    // === Gentle Slope (6dB/oct) ===
+    #[name = "Low Pass 6dB"]
+    LowPass6dB,
+    #[name = "High Pass 6dB"]
+    HighPass6dB,
+
+    // === EQ Types ===
+    #[name = "Peaking EQ"]
+    Peaking,
+    #[name = "Low Shelf"]
+    LowShelf,
+    #[name = "High Shelf"]
+    HighShelf,
+    #[name = "Tilt"]
+    Tilt,
+
+    // === Crossover (Linkwitz-Riley) ===
+    #[name = "LR Low Pass"]
+    LinkwitzRileyLP,
+    #[name = "LR High Pass"]
+    LinkwitzRileyHP,
+
+    // === Butterworth (maximally flat) ===
+    #[name = "Butterworth LP"]
+    ButterworthLP,
+    #[name = "Butterworth HP"]
+    ButterworthHP,
+
+    // === Band Pass Variations ===
+    /// Constant skirt gain, peaks at a gain of Q
+    #[name = "Band Pass Q Gain"]
+    BandPassQGain,
+
+    // === Character / Creative ===
+    #[name = "Warmth"]
+    Warmth,
+    #[name = "Brightness"]
+    Brightness,
+    #[name = "Presence"]
+    Presence,
+    #[name = "Air"]
+    Air,
+    #[name = "Sub Bass"]
+    SubBass,
+    #[name = "Vocal"]
+    Vocal,
+
+    // === Utility ===
+    #[name = "DC Block"]
+    DCBlock,
+    #[name = "Unity"]
+    Unity,
+}
+
+/// How the analog prototype of a filter is turned into digital coefficients.
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum Design {
+    /// The bilinear transform from the RBJ cookbook. Curves get squashed towards Nyquist.
+    #[name = "Bilinear"]
+    Bilinear,
+    /// Magnitude matched to the analog prototype, so curves near Nyquist keep their shape at
+    /// every sample rate.
+    #[name = "Analog Matched"]
+    Matched,
+}
+
+impl FilterType {
+    /// Compute biquad coefficients for this filter type.
+    pub fn compute_coefficients(
+        self,
+        freq: f32,
+        q: f32,
+        gain_db: f32,
+        sample_rate: f32,
+    ) -> BiquadCoefficients {
+        let ctx = FilterContext::new(freq, q, gain_db, sample_rate);
+
+        match self {
+            // Basic filters
+            Self::LowPass => Self::lowpass(&ctx),
+            Self::HighPass => Self::highpass(&ctx),
+            Self::BandPass => Self::bandpass(&ctx),
+            Self::Notch => Self::notch(&ctx),
+            Self::AllPass => Self::allpass(&ctx),
+
+            // Gentle slope (6dB/oct)
+            Self::LowPass6dB => Self::lowpass_6db(&ctx),
+            Self::HighPass6dB => Self::highpass_6db(&ctx),
+
+            // EQ types
+            Self::Peaking => Self::peaking(&ctx),
+            Self::LowShelf => Self::low_shelf(&ctx),
+            Self::HighShelf => Self::high_shelf(&ctx),
+            Self::Tilt => Self::tilt(&ctx, gain_db),
+
+            // Crossover (Linkwitz-Riley)
+            Self::LinkwitzRileyLP => Self::lowpass_with_q(&ctx, 0.5),
+            Self::LinkwitzRileyHP => Self::highpass_with_q(&ctx, 0.5),
+
+            // Butterworth
+            Self::ButterworthLP => Self::lowpass_with_q(&ctx, std::f32::consts::FRAC_1_SQRT_2),
+            Self::ButterworthHP => Self::highpass_with_q(&ctx, std::f32::consts::FRAC_1_SQRT_2),
+
+            // Band pass variations
+            Self::BandPassQGain => Self::bandpass_q_gain(&ctx, q),
+
+            // Character filters
+            Self::Warmth => Self::shelf_character(&ctx, gain_db, ShelfType::Low, 0.6),
+            Self::Brightness => Self::shelf_character(&ctx, gain_db, ShelfType::High, 0.7),
+            Self::Air => Self::shelf_character(&ctx, gain_db, ShelfType::High, 0.5),
+            Self::SubBass => Self::shelf_character(&ctx, gain_db, ShelfType::Low, 0.8),
+            Self::Presence => Self::peaking_character(&ctx, gain_db, 1.5),
+            Self::Vocal => Self::peaking_character(&ctx, gain_db, 2.0),
+
+            // Utility
+            Self::DCBlock => Self::dc_block(sample_rate),
+            Self::Unity => BiquadCoefficients::unity(),
+        }
+    }
+
+    /// Compute biquad coefficients for this filter type with the given design method.
+    ///
+    /// Only the low and high passes, peaking and shelving types have a matched design, the
+    /// others always use the bilinear transform.
+    pub fn compute_coefficients_with(
+        self,
+        design: Design,
+        freq: f32,
+        q: f32,
+        gain_db: f32,
+        sample_rate: f32,
+    ) -> BiquadCoefficients {
+        match design {
+            Design::Bilinear => self.compute_coefficients(freq, q, gain_db, sample_rate),
+            Design::Matched => {
+                let ctx = FilterContext::new(freq, q, gain_db, sample_rate);
+                self.matched_coefficients(&ctx, q, gain_db)
+                    .unwrap_or_else(|| self.compute_coefficients(freq, q, gain_db, sample_rate))
+            }
+        }
+    }
+
+    fn matched_coefficients(
+        self,
+        ctx: &FilterContext,
+        q: f32,
+        gain_db: f32,
+    ) -> Option<BiquadCoefficients> {
+        let w0 = ctx.w0 as f64;
+        let q = q as f64;
+        let a = ctx.a as f64;
+        // The character filters never go below 3 dB
+        let character_a = 10.0f64.powf(gain_db.max(3.0) as f64 / 40.0);
+        let butterworth_q = std::f64::consts::FRAC_1_SQRT_2;
+
+        let coeffs = match self {
+            Self::LowPass => matched::lowpass(w0, q),
+            Self::HighPass => matched::highpass(w0, q),
+            Self::Peaking => matched::peaking(w0, q, a),
+            Self::LowShelf => matched::low_shelf(w0, q, a),
+            Self::HighShelf => matched::high_shelf(w0, q, a),
+            Self::LinkwitzRileyLP => matched::lowpass(w0, 0.5),
+            Self::LinkwitzRileyHP => matched::highpass(w0, 0.5),
+            Self::ButterworthLP => matched::lowpass(w0, butterworth_q),
+            Self::ButterworthHP => matched::highpass(w0, butterworth_q),
+            Self::Warmth => matched::low_shelf(w0, 0.6, character_a),
+            Self::Brightness => matched::high_shelf(w0, 0.7, character_a),
+            Self::Air => matched::high_shelf(w0, 0.5, character_a),
+            Self::SubBass => matched::low_shelf(w0, 0.8, character_a),
+            Self::Presence => matched::peaking(w0, 1.5, character_a),
+            Self::Vocal => matched::peaking(w0, 2.0, character_a),
+            _ => return None,
+        };
+
+        Some(coeffs)
+    }
+
+    // ========================================
+    // Basic Filters
+    // ========================================
+
+    fn lowpass(ctx: &FilterContext) -> BiquadCoefficients {
+        let b1 = 1.0 - ctx.cos_w0;
+        let b0 = b1 / 2.0;
+        BiquadCoefficients::from_raw(
+            b0,
+            b1,
+            b0,
+            1.0 + ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha,
+        )
+    }
+
+    fn highpass(ctx: &FilterContext) -> BiquadCoefficients {
+        let b1 = -(1.0 + ctx.cos_w0);
+        let b0 = (1.0 + ctx.cos_w0) / 2.0;
+        BiquadCoefficients::from_raw(
+            b0,
+            b1,
+            b0,
+            1.0 + ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha,
+        )
+    }
+
+    fn lowpass_with_q(ctx: &FilterContext, q: f32) -> BiquadCoefficients {
+        let alpha = ctx.alpha_with_q(q);
+        let b1 = 1.0 - ctx.cos_w0;
+        let b0 = b1 / 2.0;
+        BiquadCoefficients::from_raw(b0, b1, b0, 1.0 + alpha, -2.0 * ctx.cos_w0, 1.0 - alpha)
+    }
+
+    fn highpass_with_q(ctx: &FilterContext, q: f32) -> BiquadCoefficients {
+        let alpha = ctx.alpha_with_q(q);
+        let b1 = -(1.0 + ctx.cos_w0);
+        let b0 = (1.0 + ctx.cos_w0) / 2.0;
+        BiquadCoefficients::from_raw(b0, b1, b0, 1.0 + alpha, -2.0 * ctx.cos_w0, 1.0 - alpha)
+    }
+
+    fn bandpass(ctx: &FilterContext) -> BiquadCoefficients {
+        BiquadCoefficients::from_raw(
+            ctx.alpha,
+            0.0,
+            -ctx.alpha,
+            1.0 + ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha,
+        )
+    }
+
+    fn bandpass_q_gain(ctx: &FilterContext, q: f32) -> BiquadCoefficients {
+        BiquadCoefficients::from_raw(
+            q * ctx.alpha,
+            0.0,
+            -q * ctx.alpha,
+            1.0 + ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha,
+        )
+    }
+
+    fn notch(ctx: &FilterContext) -> BiquadCoefficients {
+        BiquadCoefficients::from_raw(
+            1.0,
+            -2.0 * ctx.cos_w0,
+            1.0,
+            1.0 + ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha,
+        )
+    }
+
+    fn allpass(ctx: &FilterContext) -> BiquadCoefficients {
+        BiquadCoefficients::from_raw(
+            1.0 - ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 + ctx.alpha,
+            1.0 + ctx.alpha,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha,
+        )
+    }
+
+    // ========================================
+    // 6dB/oct (1-pole approximation)
+    // ========================================
+
+    fn lowpass_6db(ctx: &FilterContext) -> BiquadCoefficients {
+        let k = ctx.w0.tan() / 2.0;
+        let norm = 1.0 / (1.0 + k);
+        let b0 = k * norm;
+        BiquadCoefficients::from_raw(b0, b0, 0.0, 1.0, (k - 1.0) * norm, 0.0)
+    }
+
+    fn highpass_6db(ctx: &FilterContext) -> BiquadCoefficients {
+        let k = ctx.w0.tan() / 2.0;
+        let norm = 1.0 / (1.0 + k);
+        BiquadCoefficients::from_raw(norm, -norm, 0.0, 1.0, (k - 1.0) * norm, 0.0)
+    }
+
+    // ========================================
+    // EQ Types
+    // ========================================
+
+    fn peaking(ctx: &FilterContext) -> BiquadCoefficients {
+        BiquadCoefficients::from_raw(
+            1.0 + ctx.alpha * ctx.a,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha * ctx.a,
+            1.0 + ctx.alpha / ctx.a,
+            -2.0 * ctx.cos_w0,
+            1.0 - ctx.alpha / ctx.a,
+        )
+    }
+
+    fn low_shelf(ctx: &FilterContext) -> BiquadCoefficients {
+        Self::shelf_coefficients(ctx, ShelfType::Low)
+    }
+
+    fn high_shelf(ctx: &FilterContext) -> BiquadCoefficients {
+        Self::shelf_coefficients(ctx, ShelfType::High)
+    }
+
+    fn tilt(ctx: &FilterContext, gain_db: f32) -> BiquadCoefficients {
+        let tilt_a = 10.0f32.powf(gain_db / 20.0);
+        let sqrt_a = tilt_a.sqrt();
+        BiquadCoefficients::from_raw(
+            sqrt_a * (sqrt_a + ctx.alpha / sqrt_a),
+            -2.0 * sqrt_a * ctx.cos_w0,
+            sqrt_a * (sqrt_a - ctx.alpha / sqrt_a),
+            sqrt_a + ctx.alpha * sqrt_a,
+            -2.0 * sqrt_a * ctx.cos_w0,
+            sqrt_a - ctx.alpha * sqrt_a,
+        )
+    }
+
+    // ========================================
+    // Shelf Helpers
+    // ========================================
+
+    fn shelf_coefficients(ctx: &FilterContext, shelf_type: ShelfType) -> BiquadCoefficients {
+        let two_sqrt_a_alpha = 2.0 * ctx.a.sqrt() * ctx.alpha;
+
+        match shelf_type {
+            ShelfType::Low => BiquadCoefficients::from_raw(
+                ctx.a * ((ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
+                2.0 * ctx.a * ((ctx.a - 1.0) - (ctx.a + 1.0) * ctx.cos_w0),
+                ctx.a * ((ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
+                (ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
+                -2.0 * ((ctx.a - 1.0) + (ctx.a + 1.0) * ctx.cos_w0),
+                (ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
+            ),
+            ShelfType::High => BiquadCoefficients::from_raw(
+                ctx.a * ((ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
+                -2.0 * ctx.a * ((ctx.a - 1.0) + (ctx.a + 1.0) * ctx.cos_w0),
+                ctx.a * ((ctx.a + 1.0) + (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
+                (ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
+                2.0 * ((ctx.a - 1.0) - (ctx.a + 1.0) * ctx.cos_w0),
+                (ctx.a + 1.0) - (ctx.a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
+            ),
+        }
+    }
+
+    // ========================================
+    // Character Filters
+    // ========================================
+
+    fn shelf_character(
+        ctx: &FilterContext,
+        gain_db: f32,
+        shelf_type: ShelfType,
+        q: f32,
+    ) -> BiquadCoefficients {
+        let a = 10.0f32.powf(gain_db.max(3.0) / 40.0);
+        let alpha = ctx.alpha_with_q(q);
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        match shelf_type {
+            ShelfType::Low => BiquadCoefficients::from_raw(
+                a * ((a + 1.0) - (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * ctx.cos_w0),
+                a * ((a + 1.0) - (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * ctx.cos_w0),
+                (a + 1.0) + (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
+            ),
+            ShelfType::High => BiquadCoefficients::from_raw(
+                a * ((a + 1.0) + (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * ctx.cos_w0),
+                a * ((a + 1.0) + (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * ctx.cos_w0 + two_sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * ctx.cos_w0),
+                (a + 1.0) - (a - 1.0) * ctx.cos_w0 - two_sqrt_a_alpha,
+            ),
+        }
+    }
+
+    fn peaking_character(ctx: &FilterContext, gain_db: f32, q: f32) -> BiquadCoefficients {
+        let a = 10.0f32.powf(gain_db.max(3.0) / 40.0);
+        let alpha = ctx.alpha_with_q(q);
+        BiquadCoefficients::from_raw(
+            1.0 + alpha * a,
+            -2.0 * ctx.cos_w0,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * ctx.cos_w0,
+            1.0 - alpha / a,
+        )
+    }
+
+    // ========================================
+    // Utility
+    // ========================================
+
+    fn dc_block(sample_rate: f32) -> BiquadCoefficients {
+        use std::f32::consts::PI;
+        let dc_w0 = 2.0 * PI * 20.0 / sample_rate;
+        let dc_cos = dc_w0.cos();
+        let dc_alpha = dc_w0.sin() / (2.0 * 0.707);
+        let b0 = (1.0 + dc_cos) / 2.0;
+        BiquadCoefficients::from_raw(
+            b0,
+            -(1.0 + dc_cos),
+            b0,
+            1.0 + dc_alpha,
+            -2.0 * dc_cos,
+            1.0 - dc_alpha,
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ShelfType {
+    Low,
+    High,
+}
@@ -0,0 +1,295 @@
+//! Low frequency oscillator for modulating filter parameters.
+//!
+//! The LFO runs in free Hz or locked to the host tempo through [`NoteDivision`]. Its output is
+//! bipolar in `[-1, 1]`. Two LFOs with the same seed produce the same random sequence, so a
+//! stereo pair only differs by its phase offset.
+
+use std::f32::consts::TAU;
+
+use nih_plug::prelude::*;
+
+/// Seed of the random shapes, shared by every LFO so channels stay in step.
+const SEED: u32 = 0x9e37_79b9;
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum LfoShape {
+    #[name = "Sine"]
+    Sine,
+    #[name = "Triangle"]
+    Triangle,
+    /// Rising saw
+    #[name = "Saw"]
+    Saw,
+    #[name = "Square"]
+    Square,
+    /// A new random value every cycle
+    #[name = "Sample & Hold"]
+    SampleAndHold,
+    /// Random values with smooth transitions between them
+    #[name = "Smooth Random"]
+    SmoothRandom,
+}
+
+/// Length of one LFO cycle when it's synced to the host tempo.
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum NoteDivision {
+    #[name = "4 Bars"]
+    FourBars,
+    #[name = "2 Bars"]
+    TwoBars,
+    #[name = "1 Bar"]
+    Bar,
+    #[name = "1/2"]
+    Half,
+    #[name = "1/4"]
+    Quarter,
+    #[name = "1/4 Dotted"]
+    QuarterDotted,
+    #[name = "1/4 Triplet"]
+    QuarterTriplet,
+    #[name = "1/8"]
+    Eighth,
+    #[name = "1/8 Dotted"]
+    EighthDotted,
+    #[name = "1/8 Triplet"]
+    EighthTriplet,
+    #[name = "1/16"]
+    Sixteenth,
+    #[name = "1/32"]
+    ThirtySecond,
+}
+
+impl NoteDivision {
+    /// Length of the division in quarter note beats (assuming 4/4).
+    pub fn beats(self) -> f32 {
+        match self {
+            Self::FourBars => 16.0,
+            Self::TwoBars => 8.0,
+            Self::Bar => 4.0,
+            Self::Half => 2.0,
+            Self::Quarter => 1.0,
+            Self::QuarterDotted => 1.5,
+            Self::QuarterTriplet => 2.0 / 3.0,
+            Self::Eighth => 0.5,
+            Self::EighthDotted => 0.75,
+            Self::EighthTriplet => 1.0 / 3.0,
+            Self::Sixteenth => 0.25,
+            Self::ThirtySecond => 0.125,
+        }
+    }
+
+    /// Frequency in Hz of one cycle per division at the given tempo.
+    pub fn rate_hz(self, tempo_bpm: f32) -> f32 {
+        tempo_bpm / 60.0 / self.beats()
+    }
+
+    /// Position within the cycle at a song position in beats, so the cycles line up with the
+    /// host's timeline.
+    pub fn phase(self, pos_beats: f64) -> f32 {
+        (pos_beats / self.beats() as f64).rem_euclid(1.0) as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Lfo {
+    shape: LfoShape,
+    /// Position within the cycle in `[0, 1)`, without the offset. Kept in double precision
+    /// so the rate doesn't drift at low frequencies.
+    phase: f64,
+    increment: f64,
+    offset: f64,
+    // Random shapes
+    rng: u32,
+    previous: f32,
+    held: f32,
+}
+
+impl Default for Lfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lfo {
+    pub fn new() -> Self {
+        let mut lfo = Self {
+            shape: LfoShape::Sine,
+            phase: 0.0,
+            increment: 0.0,
+            offset: 0.0,
+            rng: SEED,
+            previous: 0.0,
+            held: 0.0,
+        };
+        lfo.reset();
+        lfo
+    }
+
+    /// Restart the cycle and the random sequence.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.rng = SEED;
+        self.previous = self.random();
+        self.held = self.random();
+    }
+
+    pub fn set_shape(&mut self, shape: LfoShape) {
+        self.shape = shape;
+    }
+
+    /// Update the rate in Hz.
+    pub fn set_rate(&mut self, rate: f32, sample_rate: f32) {
+        self.increment = rate as f64 / sample_rate as f64;
+    }
+
+    /// Shift this LFO against the others, as a fraction of a cycle.
+    pub fn set_phase_offset(&mut self, offset: f32) {
+        self.offset = offset as f64;
+    }
+
+    /// Jump to a position within the cycle, e.g. to follow the host transport.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = (phase as f64).rem_euclid(1.0);
+    }
+
+    /// Return the current value and advance by one sample.
+    pub fn process(&mut self) -> f32 {
+        let shifted = (self.phase + self.offset).fract();
+        let phase = shifted as f32;
+        let value = match self.shape {
+            LfoShape::Sine => (phase * TAU).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * ((phase + 0.25).fract() - 0.5).abs(),
+            LfoShape::Saw => 2.0 * phase - 1.0,
+            LfoShape::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            LfoShape::SampleAndHold => self.held,
+            LfoShape::SmoothRandom => {
+                // Cosine interpolation has no corners where the segments meet
+                let t = 0.5 - 0.5 * (phase * TAU * 0.5).cos();
+                self.previous + (self.held - self.previous) * t
+            }
+        };
+
+        self.phase = (self.phase + self.increment).fract();
+        // A new random value every time the shifted cycle starts over
+        if (self.phase + self.offset).fract() < shifted {
+            self.previous = self.held;
+            self.held = self.random();
+        }
+
+        value
+    }
+
+    /// Uniform random value in `[-1, 1]` (xorshift).
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn cycle(shape: LfoShape, offset: f32) -> Vec<f32> {
+        let mut lfo = Lfo::new();
+        lfo.set_shape(shape);
+        lfo.set_rate(1.0, SAMPLE_RATE);
+        lfo.set_phase_offset(offset);
+        (0..SAMPLE_RATE as usize).map(|_| lfo.process()).collect()
+    }
+
+    #[test]
+    fn test_lfo_shapes() {
+        // Values just after the start, a quarter, half and three quarters of the cycle
+        let expected = [
+            (LfoShape::Sine, [0.0, 1.0, 0.0, -1.0]),
+            (LfoShape::Triangle, [0.0, 1.0, 0.0, -1.0]),
+            (LfoShape::Saw, [-1.0, -0.5, 0.0, 0.5]),
+            (LfoShape::Square, [1.0, 1.0, -1.0, -1.0]),
+        ];
+        for (shape, values) in expected {
+            let output = cycle(shape, 0.0);
+            for (quarter, value) in values.into_iter().enumerate() {
+                let actual = output[quarter * output.len() / 4 + 1];
+                assert!(
+                    (actual - value).abs() < 1e-3,
+                    "{:?} at quarter {}: {}",
+                    shape,
+                    quarter,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lfo_phase_offset_delays_the_cycle() {
+        for shape in [
+            LfoShape::Triangle,
+            LfoShape::SampleAndHold,
+            LfoShape::SmoothRandom,
+        ] {
+            let mut left = Lfo::new();
+            let mut right = Lfo::new();
+            for lfo in [&mut left, &mut right] {
+                lfo.set_shape(shape);
+                lfo.set_rate(4.0, SAMPLE_RATE);
+            }
+            right.set_phase_offset(0.25);
+
+            // A quarter cycle later the left LFO is where the right one started
+            let right_output: Vec<f32> = (0..24000).map(|_| right.process()).collect();
+            let left_output: Vec<f32> = (0..27000).map(|_| left.process()).collect();
+            for i in 0..24000 {
+                assert!(
+                    (left_output[i + 3000] - right_output[i]).abs() < 1e-3,
+                    "{:?} differs at {}",
+                    shape,
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lfo_random_shapes() {
+        let held = cycle(LfoShape::SampleAndHold, 0.0);
+        let smooth = cycle(LfoShape::SmoothRandom, 0.0);
+
+        // One value per cycle, and no jumps when the values are interpolated
+        let mut lfo = Lfo::new();
+        lfo.set_shape(LfoShape::SampleAndHold);
+        lfo.set_rate(10.0, SAMPLE_RATE);
+        let values: Vec<f32> = (0..SAMPLE_RATE as usize).map(|_| lfo.process()).collect();
+        let mut distinct = values.clone();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 10);
+        assert!(values.iter().all(|value| value.abs() <= 1.0));
+        assert!(held.iter().all(|value| *value == held[0]));
+
+        let max_step = smooth
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_step < 1e-3, "Smooth random jumps by {}", max_step);
+    }
+
+    #[test]
+    fn test_note_division_follows_tempo() {
+        assert_eq!(NoteDivision::Quarter.rate_hz(120.0), 2.0);
+        assert_eq!(NoteDivision::Bar.rate_hz(120.0), 0.5);
+        assert!((NoteDivision::EighthTriplet.rate_hz(120.0) - 6.0).abs() < 1e-5);
+        assert_eq!(NoteDivision::Bar.phase(6.0), 0.5);
+        assert_eq!(NoteDivision::Eighth.phase(-0.25), 0.5);
+    }
+}
@@ -0,0 +1,19 @@
+//! DSP building blocks shared between the cantrip plugins.
+
+pub mod adsr;
+pub mod biquad;
+pub mod coefficients;
+pub mod complex;
+pub mod convolution;
+pub mod dynamics;
+pub mod envelope;
+pub mod fft;
+pub mod filter_type;
+pub mod lfo;
+mod matched;
+pub mod oversampling;
+pub mod prototype;
+pub mod response;
+pub mod svf;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
@@ -0,0 +1,267 @@
+//! Analog-matched biquad design.
+//!
+//! The bilinear transform maps the whole analog frequency axis onto the range below Nyquist, so
+//! a peak or shelf close to Nyquist gets squashed ("cramped") compared to its analog prototype.
+//! The design here follows Martin Vicanek's "Matched Second Order Digital Filters": the poles
+//! come from the impulse invariant transform, and the zeros are chosen so the magnitude response
+//! equals the analog one exactly at DC, at the design frequency and at Nyquist. In between the
+//! error stays within about a decibel for musically useful Qs, independent of the sample rate.
+
+use std::f64::consts::PI;
+
+use crate::coefficients::BiquadCoefficients;
+
+/// Squared magnitude of an analog prototype, as a function of frequency relative to the design
+/// frequency.
+type AnalogResponse = dyn Fn(f64) -> f64;
+
+/// Impulse invariant poles for a natural frequency of `wp` radians per sample, returned as
+/// `(a1, a2)`.
+fn poles(wp: f64, q: f64) -> (f64, f64) {
+    let zeta = 1.0 / (2.0 * q);
+    let decay = (-zeta * wp).exp();
+    let a1 = if zeta <= 1.0 {
+        -2.0 * decay * ((1.0 - zeta * zeta).sqrt() * wp).cos()
+    } else {
+        -2.0 * decay * ((zeta * zeta - 1.0).sqrt() * wp).cosh()
+    };
+
+    (a1, decay * decay)
+}
+
+/// The squared magnitude of a second-order polynomial in z^-1 is linear in these three basis
+/// functions of frequency, which turns the matching into a set of linear equations.
+fn basis(w: f64) -> (f64, f64, f64) {
+    let phi1 = (w / 2.0).sin().powi(2);
+    let phi0 = 1.0 - phi1;
+    (phi0, phi1, 4.0 * phi0 * phi1)
+}
+
+/// `1 + a1*z^-1 + a2*z^-2` expressed in the basis from [`basis()`].
+fn denominator_basis(a1: f64, a2: f64) -> (f64, f64, f64) {
+    ((1.0 + a1 + a2).powi(2), (1.0 - a1 + a2).powi(2), -4.0 * a2)
+}
+
+/// Design a biquad whose magnitude response matches an analog second-order section.
+///
+/// `w0` is the design frequency in radians per sample. The analog poles have a natural
+/// frequency of `pole_freq` (relative to the design frequency) and a quality factor of
+/// `pole_q`. `magnitude_sq` is the squared magnitude of the analog prototype.
+fn matched_coefficients(
+    w0: f64,
+    pole_freq: f64,
+    pole_q: f64,
+    magnitude_sq: &AnalogResponse,
+) -> BiquadCoefficients {
+    let (a1, a2) = poles(w0 * pole_freq, pole_q);
+    let (phi0, phi1, phi2) = basis(w0);
+    let (big_a0, big_a1, big_a2) = denominator_basis(a1, a2);
+    let denominator_sq = big_a0 * phi0 + big_a1 * phi1 + big_a2 * phi2;
+
+    // Numerator in the same basis, matched at DC, Nyquist and the design frequency
+    let big_b0 = magnitude_sq(0.0) * big_a0;
+    let big_b1 = magnitude_sq(PI / w0) * big_a1;
+    let big_b2 = (magnitude_sq(1.0) * denominator_sq - big_b0 * phi0 - big_b1 * phi1) / phi2;
+
+    // Back from the basis to polynomial coefficients, picking the minimum phase solution
+    let sqrt_b0 = big_b0.sqrt();
+    let sqrt_b1 = big_b1.sqrt();
+    let w = 0.5 * (sqrt_b0 + sqrt_b1);
+    let b0 = 0.5 * (w + (w * w + big_b2).max(0.0).sqrt());
+    let b1 = 0.5 * (sqrt_b0 - sqrt_b1);
+    let b2 = -big_b2 / (4.0 * b0);
+
+    to_coefficients(b0, b1, b2, a1, a2)
+}
+
+fn to_coefficients(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> BiquadCoefficients {
+    BiquadCoefficients {
+        b0: b0 as f32,
+        b1: b1 as f32,
+        b2: b2 as f32,
+        a1: a1 as f32,
+        a2: a2 as f32,
+    }
+}
+
+/// Swap numerator and denominator. Cuts are designed as the inverse of the matching boost,
+/// since the three matching points cannot describe a narrow dip close to Nyquist.
+fn invert(coeffs: BiquadCoefficients) -> BiquadCoefficients {
+    BiquadCoefficients::from_raw(1.0, coeffs.a1, coeffs.a2, coeffs.b0, coeffs.b1, coeffs.b2)
+}
+
+/// Matched low pass with resonance `q`.
+pub(crate) fn lowpass(w0: f64, q: f64) -> BiquadCoefficients {
+    // The analog low pass has next to nothing left at Nyquist, so instead of matching there the
+    // numerator is kept first order and matched at DC and the design frequency only
+    let (a1, a2) = poles(w0, q);
+    let (phi0, phi1, phi2) = basis(w0);
+    let (big_a0, big_a1, big_a2) = denominator_basis(a1, a2);
+    let denominator_sq = big_a0 * phi0 + big_a1 * phi1 + big_a2 * phi2;
+
+    let big_b0 = big_a0;
+    let big_b1 = ((denominator_sq * q * q - big_b0 * phi0) / phi1).max(0.0);
+    let b0 = 0.5 * (big_b0.sqrt() + big_b1.sqrt());
+    let b1 = big_b0.sqrt() - b0;
+
+    to_coefficients(b0, b1, 0.0, a1, a2)
+}
+
+/// Matched high pass with resonance `q`.
+pub(crate) fn highpass(w0: f64, q: f64) -> BiquadCoefficients {
+    // A double zero at DC fixes the numerator up to its gain, which is matched at the design
+    // frequency
+    let (a1, a2) = poles(w0, q);
+    let (phi0, phi1, phi2) = basis(w0);
+    let (big_a0, big_a1, big_a2) = denominator_basis(a1, a2);
+    let denominator_sq = big_a0 * phi0 + big_a1 * phi1 + big_a2 * phi2;
+    let b0 = q * denominator_sq.sqrt() / (4.0 * phi1);
+
+    to_coefficients(b0, -2.0 * b0, b0, a1, a2)
+}
+
+/// Matched peaking EQ, `a` is the square root of the linear gain as in the RBJ cookbook.
+pub(crate) fn peaking(w0: f64, q: f64, a: f64) -> BiquadCoefficients {
+    if a < 1.0 {
+        return invert(peaking(w0, q, 1.0 / a));
+    }
+    matched_coefficients(w0, 1.0, a * q, &peaking_response(q, a))
+}
+
+/// Matched low shelf, `a` is the square root of the linear gain as in the RBJ cookbook.
+pub(crate) fn low_shelf(w0: f64, q: f64, a: f64) -> BiquadCoefficients {
+    if a < 1.0 {
+        return invert(low_shelf(w0, q, 1.0 / a));
+    }
+    matched_coefficients(w0, 1.0 / a.sqrt(), q, &low_shelf_response(q, a))
+}
+
+/// Matched high shelf, `a` is the square root of the linear gain as in the RBJ cookbook.
+pub(crate) fn high_shelf(w0: f64, q: f64, a: f64) -> BiquadCoefficients {
+    // The high shelf equals A^2 over the low shelf. Going through the low shelf keeps the poles
+    // below the design frequency, where impulse invariance is still accurate
+    let mut coeffs = invert(low_shelf(w0, q, a));
+    coeffs.b0 *= (a * a) as f32;
+    coeffs.b1 *= (a * a) as f32;
+    coeffs.b2 *= (a * a) as f32;
+    coeffs
+}
+
+// Squared magnitudes of the analog prototypes from the RBJ cookbook, normalized to a design
+// frequency of 1
+
+fn peaking_response(q: f64, a: f64) -> impl Fn(f64) -> f64 {
+    move |w| {
+        ((1.0 - w * w).powi(2) + (a * w / q).powi(2))
+            / ((1.0 - w * w).powi(2) + (w / (a * q)).powi(2))
+    }
+}
+
+fn low_shelf_response(q: f64, a: f64) -> impl Fn(f64) -> f64 {
+    move |w| {
+        let w2 = w * w;
+        a * a * ((a - w2).powi(2) + a * w2 / (q * q)) / ((1.0 - a * w2).powi(2) + a * w2 / (q * q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter_type::{Design, FilterType};
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn lowpass_response(q: f64) -> impl Fn(f64) -> f64 {
+        move |w| 1.0 / ((1.0 - w * w).powi(2) + (w / q).powi(2))
+    }
+
+    fn highpass_response(q: f64) -> impl Fn(f64) -> f64 {
+        move |w| w.powi(4) / ((1.0 - w * w).powi(2) + (w / q).powi(2))
+    }
+
+    fn high_shelf_response(q: f64, a: f64) -> impl Fn(f64) -> f64 {
+        move |w| {
+            let w2 = w * w;
+            a * a * ((1.0 - a * w2).powi(2) + a * w2 / (q * q))
+                / ((a - w2).powi(2) + a * w2 / (q * q))
+        }
+    }
+
+    /// Largest deviation in dB from the analog prototype between 20 Hz and 20 kHz, ignoring
+    /// frequencies where the analog response is already below -40 dB.
+    fn max_error_db(coeffs: &BiquadCoefficients, freq: f64, magnitude_sq: &AnalogResponse) -> f64 {
+        (0..200)
+            .map(|i| 20.0 * 1000.0f64.powf(i as f64 / 199.0))
+            .filter_map(|f| {
+                let analog_db = 10.0 * magnitude_sq(f / freq).log10();
+                let digital_db = coeffs.magnitude_db(f as f32, SAMPLE_RATE as f32) as f64;
+                (analog_db > -40.0).then(|| (digital_db - analog_db).abs())
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_matched_follows_analog_prototype() {
+        for freq in [100.0, 1000.0, 5000.0, 10000.0, 15000.0] {
+            for q in [0.5, 0.707, 1.0] {
+                for gain_db in [12.0, -12.0] {
+                    let w0 = 2.0 * PI * freq / SAMPLE_RATE;
+                    let a = 10.0f64.powf(gain_db / 40.0);
+
+                    let designs: [(BiquadCoefficients, Box<AnalogResponse>); 5] = [
+                        (lowpass(w0, q), Box::new(lowpass_response(q))),
+                        (highpass(w0, q), Box::new(highpass_response(q))),
+                        (peaking(w0, q, a), Box::new(peaking_response(q, a))),
+                        (low_shelf(w0, q, a), Box::new(low_shelf_response(q, a))),
+                        (high_shelf(w0, q, a), Box::new(high_shelf_response(q, a))),
+                    ];
+                    for (coeffs, magnitude_sq) in &designs {
+                        let error = max_error_db(coeffs, freq, magnitude_sq);
+                        assert!(
+                            error < 1.5,
+                            "{freq} Hz, Q {q}, {gain_db} dB is off by {error} dB"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_matched_peak_keeps_shape_across_sample_rates() {
+        // A 12 dB boost at 16 kHz, read 2 kHz above the centre
+        let gain_at = |design: Design, sample_rate: f32| {
+            FilterType::Peaking
+                .compute_coefficients_with(design, 16000.0, 1.0, 12.0, sample_rate)
+                .magnitude_db(18000.0, sample_rate)
+        };
+
+        let bilinear =
+            (gain_at(Design::Bilinear, 44100.0) - gain_at(Design::Bilinear, 96000.0)).abs();
+        let matched = (gain_at(Design::Matched, 44100.0) - gain_at(Design::Matched, 96000.0)).abs();
+        assert!(bilinear > 2.0, "bilinear only differs by {bilinear} dB");
+        assert!(matched < 0.5, "matched differs by {matched} dB");
+    }
+
+    #[test]
+    fn test_matched_cut_mirrors_boost() {
+        let w0 = 2.0 * PI * 12000.0 / SAMPLE_RATE;
+        let a = 10.0f64.powf(9.0 / 40.0);
+        let pairs = [
+            (peaking(w0, 0.707, a), peaking(w0, 0.707, 1.0 / a)),
+            (low_shelf(w0, 0.707, a), low_shelf(w0, 0.707, 1.0 / a)),
+            (high_shelf(w0, 0.707, a), high_shelf(w0, 0.707, 1.0 / a)),
+        ];
+
+        for (boost, cut) in pairs {
+            for freq in [50.0, 1000.0, 12000.0, 20000.0] {
+                let boost_db = boost.magnitude_db(freq, SAMPLE_RATE as f32);
+                let cut_db = cut.magnitude_db(freq, SAMPLE_RATE as f32);
+                assert!(
+                    (boost_db + cut_db).abs() < 0.01,
+                    "{boost_db} vs {cut_db} dB at {freq} Hz"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,562 @@
+//! Oversampling by cascaded half-band stages.
+//!
+//! Every stage doubles the sample rate on the way up and halves it again on the way down, so
+//! 2x, 4x and 8x use one, two and three stages. The stages are polyphase half-band filters:
+//! half of their taps are zero, and each branch only ever sees every other sample. Linear phase
+//! stages are windowed-sinc FIR filters, minimum phase stages are two chains of first-order
+//! allpasses (the structure used by Laurent de Soras' HIIR library) with far less latency.
+
+use std::f64::consts::PI;
+
+use nih_plug::prelude::*;
+
+/// Highest supported oversampling ratio.
+pub const MAX_FACTOR: usize = 8;
+
+/// Number of half-band stages needed for [`MAX_FACTOR`].
+const MAX_STAGES: usize = 3;
+
+/// Non-zero side taps of the FIR half-band for each stage. The first stage needs the steepest
+/// transition, later stages only have to reject images far above the audible range.
+const FIR_TAPS: [usize; MAX_STAGES] = [64, 12, 12];
+
+/// Kaiser window shape, gives about 90dB of stopband attenuation.
+const KAISER_BETA: f64 = 9.0;
+
+/// Allpass coefficients and normalized transition bandwidth of the IIR half-band for each stage.
+const IIR_COEFS: [usize; MAX_STAGES] = [12, 4, 4];
+const IIR_TRANSITION: [f64; MAX_STAGES] = [0.04, 0.2, 0.2];
+
+/// Largest entry of [`FIR_TAPS`] and [`IIR_COEFS`].
+const MAX_FIR_TAPS: usize = 64;
+const MAX_IIR_COEFS: usize = 12;
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum OversamplingFactor {
+    #[name = "Off"]
+    Off,
+    #[name = "2x"]
+    X2,
+    #[name = "4x"]
+    X4,
+    #[name = "8x"]
+    X8,
+}
+
+impl OversamplingFactor {
+    /// Number of half-band stages for this factor.
+    pub fn stages(self) -> usize {
+        match self {
+            Self::Off => 0,
+            Self::X2 => 1,
+            Self::X4 => 2,
+            Self::X8 => 3,
+        }
+    }
+
+    /// Ratio between the oversampled and the host sample rate.
+    pub fn ratio(self) -> usize {
+        1 << self.stages()
+    }
+}
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum OversamplingPhase {
+    /// FIR half-bands, keeps the phase intact at the cost of latency
+    #[name = "Linear Phase"]
+    Linear,
+    /// IIR half-bands, hardly any latency but the phase shifts towards the top of the spectrum
+    #[name = "Minimum Phase"]
+    Minimum,
+}
+
+/// Oversampler for a single channel.
+///
+/// Either wrap the processing in [`process()`][Self::process()], or call
+/// [`upsample()`][Self::upsample()] and [`downsample()`][Self::downsample()] yourself when
+/// several channels have to be processed together.
+#[derive(Clone, Copy, Debug)]
+pub struct Oversampler {
+    factor: OversamplingFactor,
+    phase: OversamplingPhase,
+    stages: [HalfBand; MAX_STAGES],
+    /// Samples at the oversampled rate for the current input sample
+    buffer: [f32; MAX_FACTOR],
+}
+
+impl Oversampler {
+    pub fn new(factor: OversamplingFactor, phase: OversamplingPhase) -> Self {
+        Self {
+            factor,
+            phase,
+            stages: [0, 1, 2].map(|stage| HalfBand::new(phase, stage)),
+            buffer: [0.0; MAX_FACTOR],
+        }
+    }
+
+    /// Switch to a different factor or phase response. Resets the filters if anything changed.
+    pub fn set_mode(&mut self, factor: OversamplingFactor, phase: OversamplingPhase) {
+        if factor != self.factor || phase != self.phase {
+            *self = Self::new(factor, phase);
+        }
+    }
+
+    /// Reset the filter state to zero.
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+
+    pub fn factor(&self) -> OversamplingFactor {
+        self.factor
+    }
+
+    pub fn phase(&self) -> OversamplingPhase {
+        self.phase
+    }
+
+    /// Ratio between the oversampled and the host sample rate.
+    pub fn ratio(&self) -> usize {
+        self.factor.ratio()
+    }
+
+    /// Delay of a round trip through [`upsample()`][Self::upsample()] and
+    /// [`downsample()`][Self::downsample()] in samples at the host sample rate. For the minimum
+    /// phase filters this is the delay at low frequencies.
+    pub fn latency(&self) -> u32 {
+        let latency: f64 = self.stages[..self.factor.stages()]
+            .iter()
+            .enumerate()
+            .map(|(stage, half_band)| half_band.latency() / (1 << stage) as f64)
+            .sum();
+
+        latency.round() as u32
+    }
+
+    /// Upsample a single sample. Returns [`ratio()`][Self::ratio()] samples at the oversampled
+    /// rate, which can be processed in place before calling [`downsample()`][Self::downsample()].
+    pub fn upsample(&mut self, input: f32) -> &mut [f32] {
+        self.buffer[0] = input;
+
+        let mut len = 1;
+        for stage in &mut self.stages[..self.factor.stages()] {
+            let mut upsampled = [0.0; MAX_FACTOR];
+            for (i, &sample) in self.buffer[..len].iter().enumerate() {
+                [upsampled[2 * i], upsampled[2 * i + 1]] = stage.upsample(sample);
+            }
+            self.buffer = upsampled;
+            len *= 2;
+        }
+
+        &mut self.buffer[..len]
+    }
+
+    /// Downsample the samples returned by the last [`upsample()`][Self::upsample()] call back to
+    /// a single sample.
+    pub fn downsample(&mut self) -> f32 {
+        let mut len = self.ratio();
+        for stage in self.stages[..self.factor.stages()].iter_mut().rev() {
+            len /= 2;
+            for i in 0..len {
+                self.buffer[i] = stage.downsample([self.buffer[2 * i], self.buffer[2 * i + 1]]);
+            }
+        }
+
+        self.buffer[0]
+    }
+
+    /// Run `f` on every sample at the oversampled rate and return the downsampled result.
+    pub fn process(&mut self, input: f32, mut f: impl FnMut(f32) -> f32) -> f32 {
+        for sample in self.upsample(input) {
+            *sample = f(*sample);
+        }
+        self.downsample()
+    }
+}
+
+/// A single 2x stage.
+// Boxing the larger FIR variant would allocate whenever the mode changes during processing
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy, Debug)]
+enum HalfBand {
+    Fir(FirHalfBand),
+    Iir(IirHalfBand),
+}
+
+impl HalfBand {
+    fn new(phase: OversamplingPhase, stage: usize) -> Self {
+        match phase {
+            OversamplingPhase::Linear => Self::Fir(FirHalfBand::new(FIR_TAPS[stage])),
+            OversamplingPhase::Minimum => {
+                Self::Iir(IirHalfBand::new(IIR_COEFS[stage], IIR_TRANSITION[stage]))
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Self::Fir(half_band) => half_band.reset(),
+            Self::Iir(half_band) => half_band.reset(),
+        }
+    }
+
+    /// Round trip delay in samples at the stage's lower sample rate.
+    fn latency(&self) -> f64 {
+        match self {
+            Self::Fir(half_band) => half_band.latency(),
+            Self::Iir(half_band) => half_band.latency(),
+        }
+    }
+
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        match self {
+            Self::Fir(half_band) => half_band.upsample(input),
+            Self::Iir(half_band) => half_band.upsample(input),
+        }
+    }
+
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        match self {
+            Self::Fir(half_band) => half_band.downsample(input),
+            Self::Iir(half_band) => half_band.downsample(input),
+        }
+    }
+}
+
+/// Linear phase half-band, a Kaiser windowed sinc.
+///
+/// A half-band FIR of length `2 * taps - 1` has a centre tap of 0.5 and every other tap around
+/// it is zero. Only the `taps` remaining coefficients are stored, the centre tap turns into a
+/// plain delay in the polyphase form.
+#[derive(Clone, Copy, Debug)]
+struct FirHalfBand {
+    coefs: [f32; MAX_FIR_TAPS],
+    taps: usize,
+    /// Input history of the upsampler, newest first
+    up_history: [f32; MAX_FIR_TAPS],
+    /// Even and odd input histories of the downsampler, newest first
+    down_even: [f32; MAX_FIR_TAPS],
+    down_odd: [f32; MAX_FIR_TAPS / 2 + 1],
+}
+
+impl FirHalfBand {
+    fn new(taps: usize) -> Self {
+        // Position of the centre tap in the full filter
+        let centre = (taps - 1) as f64;
+
+        let mut coefs = [0.0; MAX_FIR_TAPS];
+        for (i, coef) in coefs[..taps].iter_mut().enumerate() {
+            // The non-zero taps sit at odd distances from the centre
+            let offset = 2.0 * i as f64 - centre;
+            let sinc = (PI * offset / 2.0).sin() / (PI * offset / 2.0);
+            let window_pos = offset / centre;
+            let window = bessel_i0(KAISER_BETA * (1.0 - window_pos * window_pos).sqrt())
+                / bessel_i0(KAISER_BETA);
+            *coef = (0.5 * sinc * window) as f32;
+        }
+
+        // Together with the centre tap the filter must have exactly unity gain at DC
+        let sum: f32 = coefs[..taps].iter().sum();
+        for coef in &mut coefs[..taps] {
+            *coef *= 0.5 / sum;
+        }
+
+        Self {
+            coefs,
+            taps,
+            up_history: [0.0; MAX_FIR_TAPS],
+            down_even: [0.0; MAX_FIR_TAPS],
+            down_odd: [0.0; MAX_FIR_TAPS / 2 + 1],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.up_history = [0.0; MAX_FIR_TAPS];
+        self.down_even = [0.0; MAX_FIR_TAPS];
+        self.down_odd = [0.0; MAX_FIR_TAPS / 2 + 1];
+    }
+
+    fn latency(&self) -> f64 {
+        // Each direction delays by the centre tap, which sits at `taps - 1` samples at the higher
+        // rate
+        (self.taps - 1) as f64
+    }
+
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        let taps = self.taps;
+        push(&mut self.up_history[..taps], input);
+
+        // The zero stuffed input leaves the even outputs to the side taps and the odd outputs to
+        // the centre tap. The factor two makes up for the stuffed zeros.
+        let even = 2.0 * dot(&self.coefs[..taps], &self.up_history[..taps]);
+        let odd = self.up_history[taps / 2 - 1];
+
+        [even, odd]
+    }
+
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        let taps = self.taps;
+        push(&mut self.down_even[..taps], input[0]);
+        push(&mut self.down_odd[..taps / 2 + 1], input[1]);
+
+        dot(&self.coefs[..taps], &self.down_even[..taps]) + 0.5 * self.down_odd[taps / 2]
+    }
+}
+
+/// Minimum phase half-band made of two parallel chains of first-order allpasses, each running at
+/// the lower sample rate.
+#[derive(Clone, Copy, Debug)]
+struct IirHalfBand {
+    /// Even coefficients belong to the first chain, odd ones to the second
+    coefs: [f32; MAX_IIR_COEFS],
+    len: usize,
+    up: [AllpassState; MAX_IIR_COEFS],
+    down: [AllpassState; MAX_IIR_COEFS],
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct AllpassState {
+    x1: f32,
+    y1: f32,
+}
+
+impl IirHalfBand {
+    fn new(len: usize, transition: f64) -> Self {
+        let mut coefs = [0.0; MAX_IIR_COEFS];
+        for (coef, value) in coefs.iter_mut().zip(allpass_coefs(len, transition)) {
+            *coef = value as f32;
+        }
+
+        Self {
+            coefs,
+            len,
+            up: [AllpassState::default(); MAX_IIR_COEFS],
+            down: [AllpassState::default(); MAX_IIR_COEFS],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.up = [AllpassState::default(); MAX_IIR_COEFS];
+        self.down = [AllpassState::default(); MAX_IIR_COEFS];
+    }
+
+    fn latency(&self) -> f64 {
+        // A first-order allpass delays low frequencies by (1 - a) / (1 + a) samples. Both
+        // directions together add up to the delay of both chains.
+        self.coefs[..self.len]
+            .iter()
+            .map(|&a| ((1.0 - a) / (1.0 + a)) as f64)
+            .sum()
+    }
+
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        let mut outputs = [input; 2];
+        for (i, (&coef, state)) in self.coefs[..self.len]
+            .iter()
+            .zip(&mut self.up[..self.len])
+            .enumerate()
+        {
+            outputs[i % 2] = allpass(coef, state, outputs[i % 2]);
+        }
+
+        outputs
+    }
+
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        let mut paths = [input[1], input[0]];
+        for (i, (&coef, state)) in self.coefs[..self.len]
+            .iter()
+            .zip(&mut self.down[..self.len])
+            .enumerate()
+        {
+            paths[i % 2] = allpass(coef, state, paths[i % 2]);
+        }
+
+        0.5 * (paths[0] + paths[1])
+    }
+}
+
+/// First-order allpass `(a + z^-1) / (1 + a*z^-1)`.
+fn allpass(coef: f32, state: &mut AllpassState, input: f32) -> f32 {
+    let output = (input - state.y1) * coef + state.x1;
+    state.x1 = input;
+    state.y1 = output;
+
+    // Anti-denormal
+    if state.y1.abs() < 1e-15 {
+        state.y1 = 0.0;
+    }
+
+    output
+}
+
+/// Allpass coefficients of an elliptic half-band with the given number of coefficients and
+/// normalized transition bandwidth, following HIIR's `PolyphaseIir2Designer`.
+fn allpass_coefs(len: usize, transition: f64) -> impl Iterator<Item = f64> {
+    let k = ((1.0 - 2.0 * transition) * PI / 4.0).tan().powi(2);
+    let kk_sqrt = (1.0 - k * k).powf(0.25);
+    let e = 0.5 * (1.0 - kk_sqrt) / (1.0 + kk_sqrt);
+    let e4 = e.powi(4);
+    let q = e * (1.0 + e4 * (2.0 + e4 * (15.0 + 150.0 * e4)));
+    let order = (2 * len + 1) as f64;
+
+    (1..=len).map(move |c| {
+        let c = c as f64;
+
+        // Theta function series for the pole positions
+        let mut numerator = 0.0;
+        let mut sign = 1.0;
+        for i in 0.. {
+            let term = q.powi(i * (i + 1)) * ((2 * i + 1) as f64 * c * PI / order).sin() * sign;
+            numerator += term;
+            sign = -sign;
+            if term.abs() < 1e-100 {
+                break;
+            }
+        }
+        let mut denominator = 0.0;
+        let mut sign = -1.0;
+        for i in 1.. {
+            let term = q.powi(i * i) * ((2 * i) as f64 * c * PI / order).cos() * sign;
+            denominator += term;
+            sign = -sign;
+            if term.abs() < 1e-100 {
+                break;
+            }
+        }
+
+        let ww = numerator * q.powf(0.25) / (denominator + 0.5);
+        let wwsq = ww * ww;
+        let x = ((1.0 - wwsq * k) * (1.0 - wwsq / k)).sqrt() / (1.0 + wwsq);
+        (1.0 - x) / (1.0 + x)
+    })
+}
+
+/// Zeroth-order modified Bessel function of the first kind, for the Kaiser window.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..50 {
+        term *= (x / (2.0 * k as f64)).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+/// Shift `input` into the front of a history buffer.
+fn push(history: &mut [f32], input: f32) {
+    history.copy_within(..history.len() - 1, 1);
+    history[0] = input;
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn modes() -> impl Iterator<Item = (OversamplingFactor, OversamplingPhase)> {
+        [
+            OversamplingFactor::X2,
+            OversamplingFactor::X4,
+            OversamplingFactor::X8,
+        ]
+        .into_iter()
+        .flat_map(|factor| {
+            [OversamplingPhase::Linear, OversamplingPhase::Minimum].map(|phase| (factor, phase))
+        })
+    }
+
+    fn sine(freq: f64, i: usize, sample_rate: f64) -> f32 {
+        (i as f64 * std::f64::consts::TAU * freq / sample_rate).sin() as f32
+    }
+
+    #[test]
+    fn test_oversampling_passband_is_transparent() {
+        for (factor, phase) in modes() {
+            for freq in [1000.0, 18000.0] {
+                let mut oversampler = Oversampler::new(factor, phase);
+
+                // RMS rather than peak, an 18kHz sine hardly ever gets sampled at its peak
+                let mut energy = 0.0;
+                for i in 0..9600 {
+                    let output = oversampler.process(sine(freq, i, SAMPLE_RATE), |x| x);
+                    if i >= 4800 {
+                        energy += output * output;
+                    }
+                }
+                let amplitude = (2.0 * energy / 4800.0).sqrt();
+                assert!(
+                    (amplitude - 1.0).abs() < 1e-3,
+                    "{:?} {:?}: expected unity gain at {}Hz, got {}",
+                    factor,
+                    phase,
+                    freq,
+                    amplitude
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_oversampling_rejects_aliases() {
+        for (factor, phase) in modes() {
+            let mut oversampler = Oversampler::new(factor, phase);
+            let oversampled_rate = SAMPLE_RATE * factor.ratio() as f64;
+
+            // Content above the host's Nyquist frequency created at the oversampled rate must
+            // not fold back down
+            let mut t = 0;
+            let mut peak = 0.0f32;
+            for i in 0..9600 {
+                let output = oversampler.process(0.0, |_| {
+                    t += 1;
+                    sine(28000.0, t, oversampled_rate)
+                });
+                if i > 4800 {
+                    peak = peak.max(output.abs());
+                }
+            }
+            assert!(
+                peak < 1e-4,
+                "{:?} {:?}: expected no aliasing, got {}",
+                factor,
+                phase,
+                peak
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversampling_latency_matches_delay() {
+        for (factor, phase) in modes() {
+            let mut oversampler = Oversampler::new(factor, phase);
+            let latency = oversampler.latency() as usize;
+
+            let mut max_error = 0.0f32;
+            for i in 0..4800 {
+                let output = oversampler.process(sine(200.0, i, SAMPLE_RATE), |x| x);
+                if i > 2400 {
+                    let expected = sine(200.0, i - latency, SAMPLE_RATE);
+                    max_error = max_error.max((output - expected).abs());
+                }
+            }
+            assert!(
+                max_error < 0.02,
+                "{:?} {:?}: output is off by up to {} with a latency of {}",
+                factor,
+                phase,
+                max_error,
+                latency
+            );
+        }
+    }
+}
@@ -0,0 +1,587 @@
+//! Higher-order classic filter designs as cascades of biquads.
+//!
+//! Every design starts from an analog low pass prototype with its cutoff at 1 rad/s, described by
+//! its poles and its zeros on the imaginary axis. Only even orders are supported, so the poles
+//! come in conjugate pairs and every pair becomes one second-order section. The sections are
+//! scaled to the cutoff, turned into high passes if needed, and digitized with the prewarped
+//! bilinear transform.
+//!
+//! The elliptic design follows Sophocles Orfanidis' "Lecture Notes on Elliptic Filter Design",
+//! which computes the Jacobi elliptic functions through descending Landen transformations.
+
+use nih_plug::prelude::*;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::coefficients::BiquadCoefficients;
+use crate::complex::Complex;
+
+/// Number of Landen transformations, enough for the moduli to reach machine precision for any
+/// usable ripple and attenuation.
+const LANDEN_STEPS: usize = 10;
+
+/// Number of second-order sections of the highest supported order.
+pub const MAX_SECTIONS: usize = 4;
+
+#[derive(Enum, PartialEq, Clone, Copy, Debug)]
+pub enum Prototype {
+    /// Maximally flat passband, 3dB down at the cutoff
+    #[name = "Butterworth"]
+    Butterworth,
+    /// Equiripple passband ending at the cutoff, steeper than Butterworth
+    #[name = "Chebyshev I"]
+    ChebyshevI,
+    /// Flat passband and an equiripple stopband that starts at the cutoff
+    #[name = "Chebyshev II"]
+    ChebyshevII,
+    /// Close to constant group delay, so transients keep their shape. 3dB down at the cutoff.
+    #[name = "Bessel"]
+    Bessel,
+    /// Equiripple passband ending at the cutoff and equiripple stopband, the steepest transition
+    /// for a given order
+    #[name = "Elliptic"]
+    Elliptic,
+}
+
+/// A pair of conjugate poles, with a pair of zeros on the imaginary axis if the response has
+/// any at finite frequencies.
+#[derive(Clone, Copy, Debug)]
+struct AnalogSection {
+    pole: Complex,
+    zero: Option<f64>,
+}
+
+impl AnalogSection {
+    const EMPTY: Self = Self {
+        pole: Complex { re: -1.0, im: 0.0 },
+        zero: None,
+    };
+}
+
+/// Analog low pass prototype of a classic design. Designing one doesn't allocate, so it can be
+/// done on the audio thread.
+#[derive(Clone, Copy, Debug)]
+pub struct AnalogPrototype {
+    sections: [AnalogSection; MAX_SECTIONS],
+    /// Number of sections in use
+    count: usize,
+    /// Linear gain at DC. The equiripple passbands of even order filters start at the bottom
+    /// of the ripple.
+    passband_gain: f64,
+}
+
+impl AnalogPrototype {
+    /// Design a prototype of the given even order, up to `2 * MAX_SECTIONS`. The passband ripple
+    /// applies to the Chebyshev I and elliptic designs, the stopband attenuation to the
+    /// Chebyshev II and elliptic designs.
+    pub fn new(prototype: Prototype, order: usize, ripple_db: f32, attenuation_db: f32) -> Self {
+        assert!(
+            order >= 2 && order.is_multiple_of(2),
+            "Only even filter orders are supported"
+        );
+        assert!(
+            order <= 2 * MAX_SECTIONS,
+            "Filter orders above {} are not supported",
+            2 * MAX_SECTIONS
+        );
+        let ripple_db = ripple_db as f64;
+        let attenuation_db = attenuation_db as f64;
+
+        let sections = match prototype {
+            Prototype::Butterworth => butterworth(order),
+            Prototype::ChebyshevI => chebyshev_i(order, ripple_db),
+            Prototype::ChebyshevII => chebyshev_ii(order, attenuation_db),
+            Prototype::Bessel => bessel(order),
+            Prototype::Elliptic => elliptic(order, ripple_db, attenuation_db),
+        };
+        let passband_gain = match prototype {
+            Prototype::ChebyshevI | Prototype::Elliptic => db_to_gain(-ripple_db),
+            _ => 1.0,
+        };
+
+        Self {
+            sections,
+            count: order / 2,
+            passband_gain,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        2 * self.count
+    }
+
+    /// Number of biquads needed for the digital filter.
+    pub fn sections(&self) -> usize {
+        self.count
+    }
+
+    /// Compute the biquads of a low pass with the prototype's response at `freq`. Writes up to
+    /// [`sections()`](Self::sections) coefficients.
+    pub fn low_pass(&self, freq: f32, sample_rate: f32, coefficients: &mut [BiquadCoefficients]) {
+        self.digitize(false, freq, sample_rate, coefficients);
+    }
+
+    /// Compute the biquads of a high pass, the low pass response mirrored around `freq` on a
+    /// logarithmic axis. Writes up to [`sections()`](Self::sections) coefficients.
+    pub fn high_pass(&self, freq: f32, sample_rate: f32, coefficients: &mut [BiquadCoefficients]) {
+        self.digitize(true, freq, sample_rate, coefficients);
+    }
+
+    fn digitize(
+        &self,
+        high_pass: bool,
+        freq: f32,
+        sample_rate: f32,
+        coefficients: &mut [BiquadCoefficients],
+    ) {
+        // Prewarped, so the cutoff lands on `freq` after the bilinear transform
+        let freq = freq.clamp(1.0, sample_rate * 0.499) as f64;
+        let warped = (PI * freq / sample_rate as f64).tan();
+
+        let sections = &self.sections[..self.count];
+        for (k, (section, coefficients)) in sections.iter().zip(coefficients).enumerate() {
+            // Analog section (n2*s^2 + n0) / (s^2 + d1*s + d0) with unity gain in the passband.
+            // The high pass substitutes `warped / s` for `s`.
+            let (pole, zero) = if high_pass {
+                (
+                    Complex::new(warped, 0.0) / section.pole,
+                    section.zero.map(|zero| warped / zero),
+                )
+            } else {
+                (
+                    section.pole * warped,
+                    section.zero.map(|zero| zero * warped),
+                )
+            };
+            let d1 = -2.0 * pole.re;
+            let d0 = pole.norm_sqr();
+            let (n2, n0) = match (zero, high_pass) {
+                (Some(zero), false) => (d0 / (zero * zero), d0),
+                (Some(zero), true) => (1.0, zero * zero),
+                (None, false) => (0.0, d0),
+                (None, true) => (1.0, 0.0),
+            };
+            let gain = if k == 0 { self.passband_gain } else { 1.0 };
+
+            // Bilinear transform with s = (1 - z^-1) / (1 + z^-1)
+            let a0 = 1.0 + d1 + d0;
+            *coefficients = BiquadCoefficients {
+                b0: (gain * (n2 + n0) / a0) as f32,
+                b1: (gain * 2.0 * (n0 - n2) / a0) as f32,
+                b2: (gain * (n2 + n0) / a0) as f32,
+                a1: (2.0 * (d0 - 1.0) / a0) as f32,
+                a2: ((1.0 - d1 + d0) / a0) as f32,
+            };
+        }
+    }
+}
+
+fn db_to_gain(db: f64) -> f64 {
+    10.0f64.powf(db / 20.0)
+}
+
+/// `epsilon` of a ripple or attenuation, the gain at the band edge is `1 / sqrt(1 + epsilon^2)`.
+fn epsilon(db: f64) -> f64 {
+    (10.0f64.powf(db / 10.0) - 1.0).sqrt()
+}
+
+/// Angle of the `k`th (1-based) pole pair of an order `order` Butterworth or Chebyshev filter,
+/// measured from the imaginary axis.
+fn pole_angle(order: usize, k: usize) -> f64 {
+    (2 * k - 1) as f64 * PI / (2 * order) as f64
+}
+
+/// Sections of an order `order` prototype, with the `k`th (1-based) one computed by `section`.
+fn sections_of(
+    order: usize,
+    section: impl Fn(usize) -> AnalogSection,
+) -> [AnalogSection; MAX_SECTIONS] {
+    let mut sections = [AnalogSection::EMPTY; MAX_SECTIONS];
+    for (k, slot) in sections[..order / 2].iter_mut().enumerate() {
+        *slot = section(k + 1);
+    }
+    sections
+}
+
+fn butterworth(order: usize) -> [AnalogSection; MAX_SECTIONS] {
+    sections_of(order, |k| {
+        let angle = pole_angle(order, k);
+        AnalogSection {
+            pole: Complex::new(-angle.sin(), angle.cos()),
+            zero: None,
+        }
+    })
+}
+
+/// The poles of a Chebyshev I filter lie on an ellipse instead of the unit circle.
+fn chebyshev_i(order: usize, ripple_db: f64) -> [AnalogSection; MAX_SECTIONS] {
+    let mu = (1.0 / epsilon(ripple_db)).asinh() / order as f64;
+    sections_of(order, |k| {
+        let angle = pole_angle(order, k);
+        AnalogSection {
+            pole: Complex::new(-mu.sinh() * angle.sin(), mu.cosh() * angle.cos()),
+            zero: None,
+        }
+    })
+}
+
+/// Chebyshev II is Chebyshev I with the frequency axis inverted, which puts the ripple in the
+/// stopband.
+fn chebyshev_ii(order: usize, attenuation_db: f64) -> [AnalogSection; MAX_SECTIONS] {
+    let mu = epsilon(attenuation_db).asinh() / order as f64;
+    sections_of(order, |k| {
+        let angle = pole_angle(order, k);
+        let inverse = Complex::new(-mu.sinh() * angle.sin(), mu.cosh() * angle.cos());
+        AnalogSection {
+            pole: (Complex::new(1.0, 0.0) / inverse).conj(),
+            zero: Some(1.0 / angle.cos()),
+        }
+    })
+}
+
+/// The poles of a Bessel filter are the roots of the reverse Bessel polynomial, scaled so the
+/// response is 3dB down at the cutoff.
+fn bessel(order: usize) -> [AnalogSection; MAX_SECTIONS] {
+    let roots = bessel_roots(order);
+    let poles = &roots[..order / 2];
+    let squared_magnitude = |w: f64| {
+        poles.iter().fold(1.0, |product, pole| {
+            let s = Complex::new(0.0, w);
+            product * pole.norm_sqr() * pole.norm_sqr()
+                / ((s - *pole).norm_sqr() * (s - pole.conj()).norm_sqr())
+        })
+    };
+
+    // The magnitude falls monotonically, and the 3dB point of the delay normalized polynomial
+    // lies somewhere below its order
+    let (mut low, mut high) = (0.0, order as f64);
+    for _ in 0..60 {
+        let middle = (low + high) / 2.0;
+        if squared_magnitude(middle) > 0.5 {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    let cutoff = (low + high) / 2.0;
+
+    sections_of(order, |k| AnalogSection {
+        pole: poles[k - 1] * (1.0 / cutoff),
+        zero: None,
+    })
+}
+
+/// Roots with a positive imaginary part of the reverse Bessel polynomial
+/// `sum (2n - k)! / (2^(n - k) k! (n - k)!) s^k`, found with the Durand-Kerner method. The
+/// first `order / 2` are set.
+fn bessel_roots(order: usize) -> [Complex; MAX_SECTIONS] {
+    // Horner's scheme, with each coefficient derived from the one above it
+    let evaluate = |s: Complex| {
+        let mut coefficient = 1.0;
+        let mut value = Complex::new(1.0, 0.0);
+        for k in (1..=order).rev() {
+            coefficient *= ((2 * order - k + 1) * k) as f64 / (2 * (order - k + 1)) as f64;
+            value = value * s + Complex::new(coefficient, 0.0);
+        }
+        (value, coefficient)
+    };
+
+    // Start on a circle with the geometric mean of the roots' magnitudes as its radius
+    let (_, constant) = evaluate(Complex::new(0.0, 0.0));
+    let radius = constant.powf(1.0 / order as f64);
+    let mut roots = [Complex::new(0.0, 0.0); 2 * MAX_SECTIONS];
+    let roots = &mut roots[..order];
+    for (i, root) in roots.iter_mut().enumerate() {
+        *root = Complex::from_polar(radius, 2.0 * PI * i as f64 / order as f64 + 0.4);
+    }
+
+    for _ in 0..500 {
+        let mut largest_step = 0.0f64;
+        for i in 0..roots.len() {
+            let root = roots[i];
+            let denominator = roots
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(Complex::new(1.0, 0.0), |product, (_, other)| {
+                    product * (root - *other)
+                });
+            let step = evaluate(root).0 / denominator;
+            roots[i] = root - step;
+            largest_step = largest_step.max(step.norm_sqr());
+        }
+        if largest_step < (radius * 1e-14).powi(2) {
+            break;
+        }
+    }
+
+    // Even order Bessel polynomials have no real roots
+    let mut upper = [Complex::new(0.0, 0.0); MAX_SECTIONS];
+    for (slot, root) in upper
+        .iter_mut()
+        .zip(roots.iter().filter(|root| root.im > 0.0))
+    {
+        *slot = *root;
+    }
+    upper
+}
+
+/// Elliptic filter with the passband edge at 1 rad/s.
+fn elliptic(order: usize, ripple_db: f64, attenuation_db: f64) -> [AnalogSection; MAX_SECTIONS] {
+    let passband_epsilon = epsilon(ripple_db);
+    let discrimination = passband_epsilon / epsilon(attenuation_db);
+    let discrimination_complement = (1.0 - discrimination * discrimination).sqrt();
+
+    // The degree equation gives the selectivity `k`, the ratio between the passband and the
+    // stopband edge, that the order and discrimination allow. Computed as its complement,
+    // since `k` itself gets very close to 1.
+    let positions = |i: usize| (2 * i - 1) as f64 / order as f64;
+    let discrimination_landen = landen(discrimination);
+    let selectivity_complement = discrimination_complement.powi(order as i32)
+        * (1..=order / 2)
+            .map(|i| sn(Complex::new(positions(i), 0.0), &discrimination_landen).re)
+            .product::<f64>()
+            .powi(4);
+    let selectivity = (1.0 - selectivity_complement * selectivity_complement).sqrt();
+    let selectivity_landen = landen(selectivity_complement);
+
+    // Shifting the argument of `cd` by this much along the imaginary axis turns its zeros into
+    // the poles of the filter
+    let shift = 2.0
+        * inverse_sn_imaginary(
+            1.0 / passband_epsilon,
+            discrimination,
+            discrimination_complement,
+        )
+        .asinh()
+        / (PI * order as f64);
+
+    sections_of(order, |i| {
+        let u = positions(i);
+        let zeta = cd(Complex::new(u, 0.0), &selectivity_landen).re;
+        let pole = cd(Complex::new(u, -shift), &selectivity_landen) * Complex::new(0.0, 1.0);
+        AnalogSection {
+            pole,
+            zero: Some(1.0 / (selectivity * zeta)),
+        }
+    })
+}
+
+/// Descending Landen sequence of moduli for a modulus with the given complement. Working from the
+/// complement keeps moduli close to 1 accurate.
+fn landen(complement: f64) -> [f64; LANDEN_STEPS] {
+    let mut moduli = [0.0; LANDEN_STEPS];
+    let mut complement = complement;
+    for modulus in &mut moduli {
+        *modulus = (1.0 - complement) / (1.0 + complement);
+        complement = 2.0 * complement.sqrt() / (1.0 + complement);
+    }
+    moduli
+}
+
+/// Jacobi `sn(uK, k)` from the Landen sequence of `k`, with `u` in units of the quarter period.
+fn sn(u: Complex, landen: &[f64; LANDEN_STEPS]) -> Complex {
+    ascend((u * FRAC_PI_2).sin(), landen)
+}
+
+/// Jacobi `cd(uK, k)` from the Landen sequence of `k`, with `u` in units of the quarter period.
+fn cd(u: Complex, landen: &[f64; LANDEN_STEPS]) -> Complex {
+    ascend((u * FRAC_PI_2).cos(), landen)
+}
+
+/// Ascending Landen transformations, from the trigonometric limit back up to modulus `k`.
+fn ascend(w: Complex, landen: &[f64; LANDEN_STEPS]) -> Complex {
+    landen.iter().rev().fold(w, |w, &modulus| {
+        w * (1.0 + modulus) / (Complex::new(1.0, 0.0) + w * w * modulus)
+    })
+}
+
+/// For `sn(juK, k) = jy`, returns `sinh(u * pi / 2)`. Working on the imaginary part keeps
+/// everything real.
+fn inverse_sn_imaginary(y: f64, modulus: f64, complement: f64) -> f64 {
+    let landen = landen(complement);
+    let mut previous = modulus;
+    let mut y = y;
+    for modulus in landen {
+        y = y / (1.0 + (1.0 + y * y * previous * previous).sqrt()) * 2.0 / (1.0 + modulus);
+        previous = modulus;
+    }
+    y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::{cascade_magnitude, cascade_magnitude_db};
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    /// Just below Nyquist, where the bilinear transform puts infinite frequency
+    const NYQUIST: f32 = 23999.0;
+
+    fn warp(freq: f32) -> f32 {
+        (std::f32::consts::PI * freq / SAMPLE_RATE).tan()
+    }
+
+    fn unwarp(warped: f32) -> f32 {
+        warped.atan() * SAMPLE_RATE / std::f32::consts::PI
+    }
+
+    fn low_pass(
+        prototype: Prototype,
+        order: usize,
+        ripple_db: f32,
+        attenuation_db: f32,
+    ) -> Vec<BiquadCoefficients> {
+        let prototype = AnalogPrototype::new(prototype, order, ripple_db, attenuation_db);
+        let mut sections = vec![BiquadCoefficients::unity(); prototype.sections()];
+        prototype.low_pass(1000.0, SAMPLE_RATE, &mut sections);
+        sections
+    }
+
+    /// Lowest and highest gain in dB over a range of frequencies.
+    fn gain_range(sections: &[BiquadCoefficients], from: f32, to: f32) -> (f32, f32) {
+        (0..=500)
+            .map(|i| from * (to / from).powf(i as f32 / 500.0))
+            .map(|freq| cascade_magnitude_db(sections, freq, SAMPLE_RATE))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), gain| {
+                (low.min(gain), high.max(gain))
+            })
+    }
+
+    fn assert_stable(sections: &[BiquadCoefficients]) {
+        for section in sections {
+            assert!(
+                section.a2.abs() < 1.0 && section.a1.abs() < 1.0 + section.a2,
+                "Unstable section {:?}",
+                section
+            );
+        }
+    }
+
+    #[test]
+    fn test_butterworth_and_bessel_cutoff() {
+        for prototype in [Prototype::Butterworth, Prototype::Bessel] {
+            for order in [2, 4, 6, 8] {
+                let sections = low_pass(prototype, order, 1.0, 60.0);
+                assert_stable(&sections);
+                let cutoff = cascade_magnitude_db(&sections, 1000.0, SAMPLE_RATE);
+                assert!(
+                    (cutoff + 3.0103).abs() < 0.01,
+                    "{:?} order {}: {}dB at the cutoff",
+                    prototype,
+                    order,
+                    cutoff
+                );
+                assert!((cascade_magnitude(&sections, 0.0, SAMPLE_RATE) - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_passband_ripple() {
+        for prototype in [Prototype::ChebyshevI, Prototype::Elliptic] {
+            for order in [2, 4, 8] {
+                for ripple_db in [0.1, 1.0, 3.0] {
+                    let sections = low_pass(prototype, order, ripple_db, 60.0);
+                    assert_stable(&sections);
+
+                    // The passband ripples between 0dB and minus the ripple, and ends at the
+                    // bottom of it
+                    let (low, high) = gain_range(&sections, 1.0, 1000.0);
+                    assert!(
+                        high.abs() < 0.01 && (low + ripple_db).abs() < 0.01,
+                        "{:?} order {}: passband from {}dB to {}dB for {}dB ripple",
+                        prototype,
+                        order,
+                        low,
+                        high,
+                        ripple_db
+                    );
+                    let edge = cascade_magnitude_db(&sections, 1000.0, SAMPLE_RATE);
+                    assert!((edge + ripple_db).abs() < 0.01);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stopband_attenuation() {
+        // At order 2, a deep stopband pushes the Chebyshev II poles down to a few Hz, where f32
+        // coefficients lose too much precision for these tolerances
+        for order in [4, 6, 8] {
+            for attenuation_db in [30.0, 60.0, 90.0] {
+                // Chebyshev II is flat up to its stopband, which starts at the cutoff
+                let sections = low_pass(Prototype::ChebyshevII, order, 1.0, attenuation_db);
+                assert_stable(&sections);
+                let (_, high) = gain_range(&sections, 1000.0, NYQUIST);
+                assert!(
+                    (high + attenuation_db).abs() < 0.05,
+                    "Chebyshev II order {}: stopband peaks at {}dB for {}dB attenuation",
+                    order,
+                    high,
+                    attenuation_db
+                );
+                let (_, peak) = gain_range(&sections, 1.0, 1000.0);
+                assert!(
+                    peak.abs() < 0.01,
+                    "Chebyshev II passband peaks at {}dB",
+                    peak
+                );
+
+                // The elliptic stopband starts somewhere below its lowest zero, depending on the
+                // order and the attenuation
+                let prototype =
+                    AnalogPrototype::new(Prototype::Elliptic, order, 1.0, attenuation_db);
+                let lowest_zero = prototype.sections[..prototype.sections()]
+                    .iter()
+                    .filter_map(|section| section.zero)
+                    .fold(f64::INFINITY, f64::min);
+                let sections = low_pass(Prototype::Elliptic, order, 1.0, attenuation_db);
+                let stopband = unwarp(warp(1000.0) * lowest_zero as f32);
+                let (_, high) = gain_range(&sections, stopband, NYQUIST);
+                assert!(
+                    (high + attenuation_db).abs() < 0.05,
+                    "Elliptic order {}: stopband peaks at {}dB for {}dB attenuation",
+                    order,
+                    high,
+                    attenuation_db
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_steeper_designs() {
+        // At the same order, the equiripple designs trade flatness for a faster rolloff
+        let gain = |prototype| {
+            let sections = low_pass(prototype, 6, 1.0, 60.0);
+            cascade_magnitude_db(&sections, 1500.0, SAMPLE_RATE)
+        };
+        assert!(gain(Prototype::Bessel) > gain(Prototype::Butterworth));
+        assert!(gain(Prototype::Butterworth) > gain(Prototype::ChebyshevI));
+        assert!(gain(Prototype::ChebyshevI) > gain(Prototype::Elliptic));
+    }
+
+    #[test]
+    fn test_high_pass_mirrors_low_pass() {
+        let prototype = AnalogPrototype::new(Prototype::Elliptic, 6, 0.5, 50.0);
+        let mut low = [BiquadCoefficients::unity(); 3];
+        let mut high = [BiquadCoefficients::unity(); 3];
+        prototype.low_pass(1000.0, SAMPLE_RATE, &mut low);
+        prototype.high_pass(1000.0, SAMPLE_RATE, &mut high);
+        assert_stable(&high);
+
+        // Mirrored around the cutoff on the prewarped frequency axis
+        for freq in [50.0, 300.0, 900.0, 1200.0, 5000.0] {
+            let mirrored = unwarp(warp(1000.0).powi(2) / warp(freq));
+            let expected = cascade_magnitude_db(&low, freq, SAMPLE_RATE);
+            let actual = cascade_magnitude_db(&high, mirrored, SAMPLE_RATE);
+            assert!(
+                (actual - expected).abs() < 0.05,
+                "{}Hz: expected {}dB, got {}dB",
+                mirrored,
+                expected,
+                actual
+            );
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Frequency response of biquad sections and cascades of them.
+//!
+//! Everything is evaluated in `f64` so the phase and group delay stay accurate close to poles
+//! and zeros on the unit circle.
+
+use std::f64::consts::TAU;
+
+use crate::coefficients::BiquadCoefficients;
+use crate::complex::Complex;
+
+/// Evaluate `c0 + c1*z^-1 + c2*z^-2` at `z = e^(jw)`, along with the polynomial weighted by the
+/// power of each term, which is what the group delay needs.
+fn polynomial(c0: f32, c1: f32, c2: f32, w: f64) -> (Complex, Complex) {
+    let z1 = Complex::new(w.cos(), -w.sin());
+    let z2 = Complex::new((2.0 * w).cos(), -(2.0 * w).sin());
+    let (c0, c1, c2) = (c0 as f64, c1 as f64, c2 as f64);
+
+    let value = Complex::new(c0 + c1 * z1.re + c2 * z2.re, c1 * z1.im + c2 * z2.im);
+    let weighted = Complex::new(c1 * z1.re + 2.0 * c2 * z2.re, c1 * z1.im + 2.0 * c2 * z2.im);
+    (value, weighted)
+}
+
+fn angular_frequency(freq: f32, sample_rate: f32) -> f64 {
+    TAU * freq as f64 / sample_rate as f64
+}
+
+impl BiquadCoefficients {
+    fn response(&self, w: f64) -> Complex {
+        let (numerator, _) = polynomial(self.b0, self.b1, self.b2, w);
+        let (denominator, _) = polynomial(1.0, self.a1, self.a2, w);
+        numerator / denominator
+    }
+
+    /// Group delay in samples at the angular frequency `w`.
+    fn delay(&self, w: f64) -> f64 {
+        let (numerator, numerator_weighted) = polynomial(self.b0, self.b1, self.b2, w);
+        let (denominator, denominator_weighted) = polynomial(1.0, self.a1, self.a2, w);
+        (numerator_weighted / numerator).re - (denominator_weighted / denominator).re
+    }
+
+    /// Linear gain at the given frequency.
+    pub fn magnitude(&self, freq: f32, sample_rate: f32) -> f32 {
+        self.response(angular_frequency(freq, sample_rate)).abs() as f32
+    }
+
+    /// Gain in dB at the given frequency.
+    pub fn magnitude_db(&self, freq: f32, sample_rate: f32) -> f32 {
+        gain_to_db(self.magnitude(freq, sample_rate))
+    }
+
+    /// Phase shift in radians at the given frequency, wrapped to `[-pi, pi]`.
+    pub fn phase(&self, freq: f32, sample_rate: f32) -> f32 {
+        self.response(angular_frequency(freq, sample_rate)).arg() as f32
+    }
+
+    /// Group delay in samples at the given frequency. It is undefined at zeros that lie exactly
+    /// on the unit circle, such as the centre of a notch.
+    pub fn group_delay(&self, freq: f32, sample_rate: f32) -> f32 {
+        self.delay(angular_frequency(freq, sample_rate)) as f32
+    }
+}
+
+/// Linear gain of a chain of sections at the given frequency.
+pub fn cascade_magnitude(sections: &[BiquadCoefficients], freq: f32, sample_rate: f32) -> f32 {
+    cascade_response(sections, angular_frequency(freq, sample_rate)).abs() as f32
+}
+
+/// Gain in dB of a chain of sections at the given frequency.
+pub fn cascade_magnitude_db(sections: &[BiquadCoefficients], freq: f32, sample_rate: f32) -> f32 {
+    gain_to_db(cascade_magnitude(sections, freq, sample_rate))
+}
+
+/// Phase shift in radians of a chain of sections at the given frequency, wrapped to `[-pi, pi]`.
+pub fn cascade_phase(sections: &[BiquadCoefficients], freq: f32, sample_rate: f32) -> f32 {
+    cascade_response(sections, angular_frequency(freq, sample_rate)).arg() as f32
+}
+
+/// Group delay in samples of a chain of sections at the given frequency.
+pub fn cascade_group_delay(sections: &[BiquadCoefficients], freq: f32, sample_rate: f32) -> f32 {
+    let w = angular_frequency(freq, sample_rate);
+    sections.iter().map(|section| section.delay(w)).sum::<f64>() as f32
+}
+
+fn cascade_response(sections: &[BiquadCoefficients], w: f64) -> Complex {
+    sections
+        .iter()
+        .fold(Complex::new(1.0, 0.0), |response, section| {
+            response * section.response(w)
+        })
+}
+
+fn gain_to_db(gain: f32) -> f32 {
+    // Floor to avoid -inf
+    20.0 * gain.max(1e-10).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    use super::*;
+    use crate::filter_type::FilterType;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const NYQUIST: f32 = SAMPLE_RATE / 2.0;
+
+    fn assert_close(actual: f32, expected: f32, what: &str) {
+        assert!(
+            (actual - expected).abs() < 1e-3 * expected.abs().max(1.0),
+            "{}: expected {}, got {}",
+            what,
+            expected,
+            actual
+        );
+    }
+
+    /// Check the gain of a filter type at DC, at its frequency and at Nyquist.
+    fn assert_gains(filter_type: FilterType, q: f32, gain_db: f32, expected: [f32; 3]) {
+        assert_gains_at(filter_type, 1000.0, q, gain_db, expected);
+    }
+
+    fn assert_gains_at(
+        filter_type: FilterType,
+        cutoff: f32,
+        q: f32,
+        gain_db: f32,
+        expected: [f32; 3],
+    ) {
+        let coeffs = filter_type.compute_coefficients(cutoff, q, gain_db, SAMPLE_RATE);
+        for (freq, expected) in [0.0, cutoff, NYQUIST].into_iter().zip(expected) {
+            let what = format!("{:?} at {}Hz", filter_type, freq);
+            assert_close(coeffs.magnitude(freq, SAMPLE_RATE), expected, &what);
+        }
+    }
+
+    #[test]
+    fn test_basic_filter_responses() {
+        // The second order low and high passes peak at Q at their cutoff
+        assert_gains(FilterType::LowPass, 2.0, 0.0, [1.0, 2.0, 0.0]);
+        assert_gains(FilterType::HighPass, 2.0, 0.0, [0.0, 2.0, 1.0]);
+        assert_gains(FilterType::BandPass, 2.0, 0.0, [0.0, 1.0, 0.0]);
+        assert_gains(FilterType::BandPassQGain, 2.0, 0.0, [0.0, 2.0, 0.0]);
+        assert_gains(FilterType::Notch, 2.0, 0.0, [1.0, 0.0, 1.0]);
+        assert_gains(FilterType::AllPass, 2.0, 0.0, [1.0, 1.0, 1.0]);
+
+        // The 6dB/oct approximation only hits -3dB at its cutoff while that is low
+        assert_gains_at(FilterType::LowPass6dB, 100.0, 2.0, 0.0, [1.0, FRAC_1_SQRT_2, 0.0]);
+        assert_gains_at(FilterType::HighPass6dB, 100.0, 2.0, 0.0, [0.0, FRAC_1_SQRT_2, 1.0]);
+
+        assert_gains(
+            FilterType::ButterworthLP,
+            2.0,
+            0.0,
+            [1.0, FRAC_1_SQRT_2, 0.0],
+        );
+        assert_gains(
+            FilterType::ButterworthHP,
+            2.0,
+            0.0,
+            [0.0, FRAC_1_SQRT_2, 1.0],
+        );
+        assert_gains(FilterType::LinkwitzRileyLP, 2.0, 0.0, [1.0, 0.5, 0.0]);
+        assert_gains(FilterType::LinkwitzRileyHP, 2.0, 0.0, [0.0, 0.5, 1.0]);
+
+        assert_gains(FilterType::Unity, 2.0, 0.0, [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_eq_filter_responses() {
+        let boost = 10.0f32.powf(12.0 / 20.0);
+        let half_boost = 10.0f32.powf(6.0 / 20.0);
+
+        assert_gains(FilterType::Peaking, 2.0, 12.0, [1.0, boost, 1.0]);
+        assert_gains(FilterType::LowShelf, 0.707, 12.0, [boost, half_boost, 1.0]);
+        assert_gains(FilterType::HighShelf, 0.707, 12.0, [1.0, half_boost, boost]);
+        assert_gains(FilterType::Tilt, 0.707, 0.0, [1.0, 1.0, 1.0]);
+
+        // The character filters are shelves and peaks that boost by at least 3dB
+        let min_boost = 10.0f32.powf(3.0 / 20.0);
+        assert_gains(FilterType::Warmth, 0.707, 12.0, [boost, half_boost, 1.0]);
+        assert_gains(
+            FilterType::SubBass,
+            0.707,
+            -6.0,
+            [min_boost, min_boost.sqrt(), 1.0],
+        );
+        assert_gains(
+            FilterType::Brightness,
+            0.707,
+            12.0,
+            [1.0, half_boost, boost],
+        );
+        assert_gains(FilterType::Air, 0.707, 12.0, [1.0, half_boost, boost]);
+        assert_gains(FilterType::Presence, 0.707, 12.0, [1.0, boost, 1.0]);
+        assert_gains(FilterType::Vocal, 0.707, 0.0, [1.0, min_boost, 1.0]);
+    }
+
+    #[test]
+    fn test_dc_block_response() {
+        let coeffs = FilterType::DCBlock.compute_coefficients(1000.0, 0.707, 0.0, SAMPLE_RATE);
+
+        assert_close(coeffs.magnitude(0.0, SAMPLE_RATE), 0.0, "DC");
+        assert_close(coeffs.magnitude(20.0, SAMPLE_RATE), 0.707, "20Hz");
+        assert_close(coeffs.magnitude(1000.0, SAMPLE_RATE), 1.0, "1kHz");
+    }
+
+    #[test]
+    fn test_phase_response() {
+        let allpass = FilterType::AllPass.compute_coefficients(1000.0, 0.707, 0.0, SAMPLE_RATE);
+        assert_close(allpass.phase(0.0, SAMPLE_RATE), 0.0, "All pass at DC");
+        assert_close(
+            allpass.phase(1000.0, SAMPLE_RATE).abs(),
+            PI,
+            "All pass at f0",
+        );
+
+        // A second order low pass lags by 90 degrees at its cutoff
+        let lowpass = FilterType::LowPass.compute_coefficients(1000.0, 0.707, 0.0, SAMPLE_RATE);
+        assert_close(
+            lowpass.phase(1000.0, SAMPLE_RATE),
+            -PI / 2.0,
+            "Low pass at f0",
+        );
+    }
+
+    #[test]
+    fn test_group_delay_matches_phase_slope() {
+        let coeffs = FilterType::Peaking.compute_coefficients(1000.0, 2.0, 12.0, SAMPLE_RATE);
+
+        for freq in [200.0, 900.0, 1000.0, 1100.0, 5000.0] {
+            let delta = 0.5;
+            let slope = (coeffs.phase(freq + delta, SAMPLE_RATE)
+                - coeffs.phase(freq - delta, SAMPLE_RATE))
+                / (2.0 * PI * 2.0 * delta / SAMPLE_RATE);
+            assert!(
+                (coeffs.group_delay(freq, SAMPLE_RATE) + slope).abs() < 0.01,
+                "At {}Hz: group delay {}, phase slope {}",
+                freq,
+                coeffs.group_delay(freq, SAMPLE_RATE),
+                -slope
+            );
+        }
+    }
+
+    #[test]
+    fn test_cascade_response() {
+        let sections = [
+            FilterType::LowPass.compute_coefficients(1000.0, 0.541, 0.0, SAMPLE_RATE),
+            FilterType::LowPass.compute_coefficients(1000.0, 1.307, 0.0, SAMPLE_RATE),
+        ];
+
+        // A fourth order Butterworth low pass is 3dB down at its cutoff
+        assert_close(
+            cascade_magnitude_db(&sections, 1000.0, SAMPLE_RATE),
+            -3.0103,
+            "f0",
+        );
+        assert_close(cascade_magnitude(&sections, 0.0, SAMPLE_RATE), 1.0, "DC");
+
+        for freq in [100.0, 1000.0, 3000.0] {
+            assert_close(
+                cascade_group_delay(&sections, freq, SAMPLE_RATE),
+                sections[0].group_delay(freq, SAMPLE_RATE)
+                    + sections[1].group_delay(freq, SAMPLE_RATE),
+                "Group delay",
+            );
+        }
+
+        // And lags by 180 degrees there
+        assert_close(
+            cascade_phase(&sections, 1000.0, SAMPLE_RATE).abs(),
+            PI,
+            "Phase at f0",
+        );
+    }
+}
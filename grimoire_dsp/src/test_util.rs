@@ -0,0 +1,26 @@
+//! Measurements shared by the filter tests of the plugins. Only built for tests, or with the
+//! `test-util` feature.
+
+use std::f64::consts::TAU;
+
+/// Gain for a sine at `freq` with the given amplitude, once `process` has settled.
+///
+/// Runs the sine for a second and takes the peak output over the second half of it. The sine is
+/// computed in `f64`, `f32` loses too much phase precision over a second.
+pub fn sine_gain(
+    mut process: impl FnMut(f32) -> f32,
+    freq: f64,
+    amplitude: f32,
+    sample_rate: f32,
+) -> f32 {
+    let samples = sample_rate as usize;
+    let mut peak = 0.0f32;
+    for i in 0..samples {
+        let input = (i as f64 * TAU * freq / sample_rate as f64).sin() as f32;
+        let output = process(input * amplitude);
+        if i > samples / 2 {
+            peak = peak.max(output.abs());
+        }
+    }
+    peak / amplitude
+}